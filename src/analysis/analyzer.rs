@@ -0,0 +1,563 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use git2::Repository;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    code, docker, frontend, layouts, metadata, models, options, packages, policy, process, texts, AppFiles, LocalApp,
+};
+use crate::cdn_client::CdnOrganization;
+use crate::gitea_client::GiteaRepo;
+
+/// An analysis that can run against any app repo, emitting findings instead of printing.
+/// Analyzers run in parallel across repos, so they must not keep state between apps
+pub trait Analyzer: Send + Sync {
+    /// Short name used on the command line and in reports, e.g. `metadata`
+    fn name(&self) -> &'static str;
+
+    /// Whether the analyzer reads the file, so that only the needed files are fetched when analyzing without cloning
+    fn wants(&self, path: &str) -> bool;
+
+    fn analyze(&self, app: &AppHandle, findings: &mut Findings) -> Result<()>;
+}
+
+/// Everything known about a single app repo
+pub struct AppHandle {
+    /// When analyzing without cloning, `app.dir` is where the app would have been cloned to
+    pub app: LocalApp,
+    pub repo: GiteaRepo,
+    /// The org as registered in the CDN, missing for test and personal orgs
+    pub org: Option<CdnOrganization>,
+    /// `None` if the app dir is not a git repository, or if the app is analyzed without cloning
+    pub git: Option<Repository>,
+    /// Where the analyzers should read files from, which is either the app dir or Gitea
+    pub files: Box<dyn AppFiles + Send>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+/// A single fact or problem about an app, e.g. kind=`package` name=`Altinn.App.Core` value=`8.0.0`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Finding {
+    pub org: String,
+    pub app: String,
+    pub analyzer: String,
+    pub severity: Severity,
+    pub kind: String,
+    pub name: String,
+    pub value: Option<String>,
+}
+
+impl Finding {
+    pub fn key(&self) -> String {
+        format!("{}/{}", self.org, self.app)
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {} {}", self.analyzer, self.kind, self.name)?;
+        match &self.value {
+            Some(value) => write!(f, ": {}", value),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Collects the findings of one analyzer for one app
+pub struct Findings {
+    org: String,
+    app: String,
+    analyzer: String,
+    findings: Vec<Finding>,
+}
+
+impl Findings {
+    pub fn new(app: &LocalApp, analyzer: &str) -> Self {
+        Findings {
+            org: app.org.clone(),
+            app: app.name.clone(),
+            analyzer: analyzer.to_string(),
+            findings: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, severity: Severity, kind: &str, name: &str, value: Option<&str>) {
+        self.findings.push(Finding {
+            org: self.org.clone(),
+            app: self.app.clone(),
+            analyzer: self.analyzer.clone(),
+            severity,
+            kind: kind.to_string(),
+            name: name.to_string(),
+            value: value.map(|v| v.to_string()),
+        });
+    }
+
+    pub fn info(&mut self, kind: &str, name: &str, value: Option<&str>) {
+        self.push(Severity::Info, kind, name, value);
+    }
+
+    pub fn warning(&mut self, kind: &str, name: &str, value: Option<&str>) {
+        self.push(Severity::Warning, kind, name, value);
+    }
+
+    pub fn error(&mut self, kind: &str, name: &str, value: Option<&str>) {
+        self.push(Severity::Error, kind, name, value);
+    }
+
+    pub fn into_inner(self) -> Vec<Finding> {
+        self.findings
+    }
+}
+
+/// Merged findings of all analyzers across all apps
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Report {
+    pub created: DateTime<Utc>,
+    pub analyzers: Vec<String>,
+    /// Keys of all analyzed apps, `org/name`
+    pub apps: Vec<String>,
+    pub findings: Vec<Finding>,
+}
+
+/// All analyzers that are part of this crate
+pub fn builtin() -> Vec<Box<dyn Analyzer>> {
+    vec![
+        Box::new(MetadataAnalyzer),
+        Box::new(PackagesAnalyzer),
+        Box::new(FrontendAnalyzer),
+        Box::new(LayoutsAnalyzer),
+        Box::new(ProcessAnalyzer),
+        Box::new(PolicyAnalyzer),
+        Box::new(TextsAnalyzer),
+        Box::new(ModelsAnalyzer),
+        Box::new(CodeAnalyzer),
+        Box::new(OptionsAnalyzer),
+        Box::new(DockerAnalyzer),
+    ]
+}
+
+pub struct MetadataAnalyzer;
+
+impl Analyzer for MetadataAnalyzer {
+    fn name(&self) -> &'static str {
+        "metadata"
+    }
+
+    fn wants(&self, path: &str) -> bool {
+        path == metadata::PATH
+    }
+
+    fn analyze(&self, app: &AppHandle, findings: &mut Findings) -> Result<()> {
+        let metadata = match metadata::load(app.files.as_ref()) {
+            metadata::MetadataStatus::Parsed(metadata) => metadata,
+            metadata::MetadataStatus::Missing => {
+                findings.warning("missing", metadata::PATH, None);
+                return Ok(());
+            }
+            metadata::MetadataStatus::Malformed(err) => {
+                findings.error("malformed", metadata::PATH, Some(&err));
+                return Ok(());
+            }
+        };
+
+        findings.info("id", &metadata.id, None);
+        for (language, title) in &metadata.title {
+            findings.info("title", language, Some(title));
+        }
+        for data_type in &metadata.data_types {
+            let class_ref = data_type.app_logic.as_ref().and_then(|l| l.class_ref.as_deref());
+            findings.info("dataType", &data_type.id, class_ref);
+        }
+        for party_type in metadata.party_types_allowed.allowed() {
+            findings.info("partyTypeAllowed", party_type, None);
+        }
+        if let Some(show) = metadata.on_entry.as_ref().and_then(|o| o.show.as_deref()) {
+            findings.info("onEntry", show, None);
+        }
+        if let Some(version) = &metadata.altinn_nuget_version {
+            findings.info("altinnNugetVersion", version, None);
+        }
+
+        Ok(())
+    }
+}
+
+pub struct PackagesAnalyzer;
+
+impl Analyzer for PackagesAnalyzer {
+    fn name(&self) -> &'static str {
+        "packages"
+    }
+
+    fn wants(&self, path: &str) -> bool {
+        path.ends_with(".csproj") || path.ends_with(packages::BUILD_PROPS) || path.ends_with(packages::PACKAGES_PROPS)
+    }
+
+    fn analyze(&self, app: &AppHandle, findings: &mut Findings) -> Result<()> {
+        let projects = packages::load(app.files.as_ref())?;
+        if !projects.iter().any(|p| p.path == packages::MAIN_PROJECT) {
+            findings.warning("missing", packages::MAIN_PROJECT, None);
+        }
+
+        for project in &projects {
+            for target_framework in &project.target_frameworks {
+                findings.info("targetFramework", &project.path, Some(target_framework));
+            }
+            for package in &project.packages {
+                findings.info("package", &package.name, package.version.as_deref());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub struct FrontendAnalyzer;
+
+impl Analyzer for FrontendAnalyzer {
+    fn name(&self) -> &'static str {
+        "frontend"
+    }
+
+    fn wants(&self, path: &str) -> bool {
+        path == frontend::PATH
+    }
+
+    fn analyze(&self, app: &AppHandle, findings: &mut Findings) -> Result<()> {
+        let version = match frontend::load(app.files.as_ref())? {
+            frontend::FrontendStatus::Referenced(version) => version,
+            frontend::FrontendStatus::Unreferenced => {
+                findings.warning("unreferenced", frontend::PATH, None);
+                return Ok(());
+            }
+            frontend::FrontendStatus::Missing => {
+                findings.warning("missing", frontend::PATH, None);
+                return Ok(());
+            }
+        };
+
+        let (kind, value) = match &version {
+            frontend::FrontendVersion::Major(v) => ("major", v),
+            frontend::FrontendVersion::Pinned(v) => ("pinned", v),
+            frontend::FrontendVersion::Custom(url) => ("custom", url),
+        };
+        findings.info("version", value, Some(kind));
+
+        Ok(())
+    }
+}
+
+pub struct LayoutsAnalyzer;
+
+impl Analyzer for LayoutsAnalyzer {
+    fn name(&self) -> &'static str {
+        "layouts"
+    }
+
+    fn wants(&self, path: &str) -> bool {
+        path.starts_with(layouts::UI_DIR) && path.ends_with(".json")
+    }
+
+    fn analyze(&self, app: &AppHandle, findings: &mut Findings) -> Result<()> {
+        let layouts = layouts::load(app.files.as_ref())?;
+        for err in &layouts.errors {
+            findings.error("malformed", layouts::UI_DIR, Some(err));
+        }
+
+        for set in &layouts.sets {
+            findings.info(
+                "layoutSet",
+                set.id.as_deref().unwrap_or("<default>"),
+                set.data_type.as_deref(),
+            );
+        }
+
+        let mut components = BTreeMap::<&str, usize>::new();
+        for (_, _, component) in layouts.components() {
+            *components.entry(&component.component_type).or_default() += 1;
+        }
+        for (component_type, count) in components {
+            findings.info("component", component_type, Some(&count.to_string()));
+        }
+
+        Ok(())
+    }
+}
+
+pub struct PolicyAnalyzer;
+
+impl Analyzer for PolicyAnalyzer {
+    fn name(&self) -> &'static str {
+        "policy"
+    }
+
+    fn wants(&self, path: &str) -> bool {
+        path == policy::PATH
+    }
+
+    fn analyze(&self, app: &AppHandle, findings: &mut Findings) -> Result<()> {
+        let Some(policy) = policy::load(app.files.as_ref())? else {
+            findings.warning("missing", policy::PATH, None);
+            return Ok(());
+        };
+
+        if let Some(level) = policy.authentication_level() {
+            findings.info("authenticationLevel", level, None);
+        }
+        for (subject, actions) in policy.permissions() {
+            let actions = actions.into_iter().collect::<Vec<_>>().join(",");
+            findings.info("permission", &subject, Some(&actions));
+        }
+        for warning in policy.warnings() {
+            findings.warning(
+                "risk",
+                warning.rule.as_deref().unwrap_or("<policy>"),
+                Some(&warning.message),
+            );
+        }
+
+        Ok(())
+    }
+}
+
+pub struct ProcessAnalyzer;
+
+impl Analyzer for ProcessAnalyzer {
+    fn name(&self) -> &'static str {
+        "process"
+    }
+
+    fn wants(&self, path: &str) -> bool {
+        path == process::PATH
+    }
+
+    fn analyze(&self, app: &AppHandle, findings: &mut Findings) -> Result<()> {
+        let Some(process) = process::load(app.files.as_ref())? else {
+            findings.warning("missing", process::PATH, None);
+            return Ok(());
+        };
+
+        for task in process.task_sequence() {
+            findings.info("task", &task.id, Some(&task.task_type.to_string()));
+        }
+        for gateway in &process.gateways {
+            findings.info("gateway", &gateway.id, Some(&gateway.kind));
+        }
+
+        Ok(())
+    }
+}
+
+pub struct TextsAnalyzer;
+
+impl Analyzer for TextsAnalyzer {
+    fn name(&self) -> &'static str {
+        "texts"
+    }
+
+    fn wants(&self, path: &str) -> bool {
+        path.starts_with(texts::TEXTS_DIR) || LayoutsAnalyzer.wants(path)
+    }
+
+    fn analyze(&self, app: &AppHandle, findings: &mut Findings) -> Result<()> {
+        let texts = texts::load(app.files.as_ref())?;
+        for err in &texts.errors {
+            findings.error("malformed", texts::TEXTS_DIR, Some(err));
+        }
+        if texts.languages.is_empty() {
+            findings.warning("missing", texts::TEXTS_DIR, None);
+            return Ok(());
+        }
+
+        for (language, resources) in &texts.languages {
+            findings.info("language", language, Some(&resources.len().to_string()));
+        }
+        for (language, keys) in texts.missing_translations() {
+            for key in keys {
+                findings.warning("missingTranslation", key, Some(language));
+            }
+        }
+        for (language, keys) in texts.empty_values() {
+            for key in keys {
+                findings.warning("emptyValue", key, Some(language));
+            }
+        }
+
+        // References are only checked in layouts that could be read
+        let layouts = match layouts::load(app.files.as_ref()) {
+            Ok(layouts) => layouts,
+            Err(err) => {
+                findings.error("malformed", layouts::UI_DIR, Some(&format!("{:#}", err)));
+                return Ok(());
+            }
+        };
+        for key in texts.unknown_references(&layouts) {
+            findings.warning("unknownReference", key, None);
+        }
+
+        Ok(())
+    }
+}
+
+pub struct ModelsAnalyzer;
+
+impl Analyzer for ModelsAnalyzer {
+    fn name(&self) -> &'static str {
+        "models"
+    }
+
+    fn wants(&self, path: &str) -> bool {
+        path.starts_with(models::MODELS_DIR) || path == metadata::PATH
+    }
+
+    fn analyze(&self, app: &AppHandle, findings: &mut Findings) -> Result<()> {
+        let models = models::load(app.files.as_ref())?;
+        for err in &models.errors {
+            findings.error("malformed", models::MODELS_DIR, Some(err));
+        }
+
+        for model in &models.models {
+            findings.info("model", &model.name, model.data_type.as_deref());
+        }
+        for data_type in &models.unmatched_data_types {
+            findings.warning("unmatchedDataType", data_type, None);
+        }
+
+        Ok(())
+    }
+}
+
+pub struct CodeAnalyzer;
+
+impl Analyzer for CodeAnalyzer {
+    fn name(&self) -> &'static str {
+        "code"
+    }
+
+    fn wants(&self, path: &str) -> bool {
+        path.ends_with(".cs") && !path.starts_with("App/models/")
+    }
+
+    fn analyze(&self, app: &AppHandle, findings: &mut Findings) -> Result<()> {
+        let code = code::load(app.files.as_ref())?;
+
+        for implementation in &code.implementations {
+            findings.info(
+                "extensionPoint",
+                &implementation.extension_point,
+                Some(&implementation.class),
+            );
+            // AppBase is inherited, not registered in DI
+            if !code.is_registered(implementation) && implementation.extension_point != "AppBase" {
+                findings.warning("unregistered", &implementation.class, Some(&implementation.path));
+            }
+        }
+        for registration in code.registrations.iter().filter(|r| r.path == code::PROGRAM_PATH) {
+            findings.info(
+                "registration",
+                &registration.service,
+                registration.implementation.as_deref(),
+            );
+        }
+
+        Ok(())
+    }
+}
+
+pub struct OptionsAnalyzer;
+
+impl Analyzer for OptionsAnalyzer {
+    fn name(&self) -> &'static str {
+        "options"
+    }
+
+    fn wants(&self, path: &str) -> bool {
+        path.starts_with(options::OPTIONS_DIR) || CodeAnalyzer.wants(path) || LayoutsAnalyzer.wants(path)
+    }
+
+    fn analyze(&self, app: &AppHandle, findings: &mut Findings) -> Result<()> {
+        let options = options::load(app.files.as_ref())?;
+        for err in &options.errors {
+            findings.error("malformed", options::OPTIONS_DIR, Some(err));
+        }
+
+        for definition in &options.definitions {
+            findings.info("options", &definition.id, Some(&definition.kind.to_string()));
+        }
+        for id in options.undefined() {
+            findings.warning("undefined", id, None);
+        }
+        for id in options.codelist_references() {
+            findings.info("codelist", id, None);
+        }
+
+        Ok(())
+    }
+}
+
+pub struct DockerAnalyzer;
+
+impl Analyzer for DockerAnalyzer {
+    fn name(&self) -> &'static str {
+        "docker"
+    }
+
+    fn wants(&self, path: &str) -> bool {
+        [
+            docker::DOCKERFILE_PATH,
+            docker::HELM_VALUES_PATH,
+            docker::HELM_CHART_PATH,
+        ]
+        .contains(&path)
+    }
+
+    fn analyze(&self, app: &AppHandle, findings: &mut Findings) -> Result<()> {
+        let Some(docker) = docker::load(app.files.as_ref())? else {
+            findings.warning("missing", docker::DOCKERFILE_PATH, None);
+            return Ok(());
+        };
+
+        let today = Utc::now().date_naive();
+        for (kind, stage) in [("runtime", docker.runtime()), ("sdk", docker.sdk())] {
+            let Some(stage) = stage else {
+                continue;
+            };
+            findings.info(kind, &stage.image, stage.tag.as_deref());
+            if let Some(date) = stage.dotnet_version().and_then(|v| docker::end_of_support(v, today)) {
+                findings.warning("endOfSupport", kind, Some(&date.to_string()));
+            }
+        }
+        for stage in docker.non_standard_images() {
+            findings.warning("nonStandardImage", &stage.image, stage.tag.as_deref());
+        }
+        for step in &docker.custom_steps {
+            findings.warning("customStep", step, None);
+        }
+        if let Some(version) = docker.helm.as_ref().and_then(|h| h.chart_version.as_deref()) {
+            findings.info("chartVersion", version, None);
+        }
+
+        Ok(())
+    }
+}
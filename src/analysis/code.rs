@@ -0,0 +1,401 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::LazyLock;
+
+use anyhow::Result;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::{print_ranking, AppFiles, LocalApp};
+
+pub const PROGRAM_PATH: &str = "App/Program.cs";
+
+/// Interfaces and base classes from app-lib that apps implement to hook into the platform
+pub const EXTENSION_POINTS: &[&str] = &[
+    "IAppOptionsProvider",
+    "IInstanceAppOptionsProvider",
+    "IDataListProvider",
+    "IInstanceDataListProvider",
+    "IInstantiationProcessor",
+    "IInstantiationValidator",
+    "IDataProcessor",
+    "IDataWriteProcessor",
+    "IFormDataValidator",
+    "IDataElementValidator",
+    "ITaskValidator",
+    "IValidator",
+    "IInstanceValidator",
+    "IProcessTaskStart",
+    "IProcessTaskEnd",
+    "IProcessTaskAbandon",
+    "IProcessEnd",
+    "IProcessExclusiveGateway",
+    "IEventHandler",
+    "IPdfFormatter",
+    "ICustomPdfHandler",
+    "IPageOrder",
+    "IUserAction",
+    "IEFormidlingMetadata",
+    "IEFormidlingReceivers",
+    "IAppModel",
+    "AppBase",
+];
+
+static CLASS_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\bclass\s+(\w+)(?:\s*<[^>]*>)?(?:\s*\([^)]*\))?\s*:\s*([^{]+?)\s*(?:where\b[^{]*)?\{").unwrap()
+});
+static REGISTRATION_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\.(Add(?:Transient|Scoped|Singleton|HttpClient))\s*<\s*([\w.]+)\s*(?:,\s*([\w.]+)\s*)?>").unwrap()
+});
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppCode {
+    /// Number of .cs files, excluding data models
+    pub files: usize,
+    pub implementations: Vec<Implementation>,
+    pub registrations: Vec<Registration>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Implementation {
+    pub class: String,
+    pub extension_point: String,
+    pub path: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Registration {
+    /// The method used, e.g. `AddTransient`
+    pub method: String,
+    pub service: String,
+    pub implementation: Option<String>,
+    pub path: String,
+}
+
+impl AppCode {
+    pub fn is_registered(&self, implementation: &Implementation) -> bool {
+        self.registrations
+            .iter()
+            .any(|r| r.implementation.as_deref().unwrap_or(&r.service) == implementation.class)
+    }
+}
+
+pub fn load(files: &(impl AppFiles + ?Sized)) -> Result<AppCode> {
+    let mut result = AppCode::default();
+
+    let paths = files.find_files(&|path| path.ends_with(".cs") && !path.starts_with("App/models/"))?;
+    result.files = paths.len();
+
+    for path in paths {
+        let Some(content) = files.read_file(&path)? else {
+            continue;
+        };
+        let content = strip_comments(&content);
+
+        for captures in CLASS_REGEX.captures_iter(&content) {
+            let class = &captures[1];
+            for base in split_base_types(&captures[2]) {
+                if EXTENSION_POINTS.contains(&base) {
+                    result.implementations.push(Implementation {
+                        class: class.to_string(),
+                        extension_point: base.to_string(),
+                        path: path.clone(),
+                    });
+                }
+            }
+        }
+
+        for captures in REGISTRATION_REGEX.captures_iter(&content) {
+            result.registrations.push(Registration {
+                method: captures[1].to_string(),
+                service: short_name(&captures[2]).to_string(),
+                implementation: captures.get(3).map(|m| short_name(m.as_str()).to_string()),
+                path: path.clone(),
+            });
+        }
+    }
+
+    Ok(result)
+}
+
+/// Removes `//` and `/* */` comments. String and char literals are kept as they are,
+/// since they can contain comment markers, e.g. `"image/*"` or `"https://"`
+fn strip_comments(content: &str) -> String {
+    let chars = content.chars().collect::<Vec<_>>();
+    let mut result = String::with_capacity(content.len());
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        match c {
+            '/' if next == Some('/') => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '/' if next == Some('*') => {
+                i += 2;
+                while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                    // Keep line breaks so that the code on either side isn't joined
+                    if chars[i] == '\n' {
+                        result.push('\n');
+                    }
+                    i += 1;
+                }
+                i += 2;
+                result.push(' ');
+            }
+            '"' | '\'' | '@' | '$' => match literal_end(&chars, i) {
+                Some(end) => {
+                    result.extend(&chars[i..end]);
+                    i = end;
+                }
+                None => {
+                    result.push(c);
+                    i += 1;
+                }
+            },
+            _ => {
+                result.push(c);
+                i += 1;
+            }
+        }
+    }
+
+    result
+}
+
+/// End of the string or char literal starting at `start`, or `None` if no literal starts there.
+/// Handles regular, verbatim (`@"..."`), interpolated (`$"..."`) and raw (`"""..."""`) strings
+fn literal_end(chars: &[char], start: usize) -> Option<usize> {
+    let mut i = start;
+    let mut interpolated = false;
+    let mut verbatim = false;
+    while let Some(c @ ('$' | '@')) = chars.get(i).copied() {
+        interpolated |= c == '$';
+        verbatim |= c == '@';
+        i += 1;
+    }
+
+    match chars.get(i) {
+        Some('\'') if i == start => {
+            i += 1;
+            while i < chars.len() && chars[i] != '\'' && chars[i] != '\n' {
+                i += if chars[i] == '\\' { 2 } else { 1 };
+            }
+            return Some((i + 1).min(chars.len()));
+        }
+        Some('"') => {}
+        _ => return None,
+    }
+
+    let quotes = chars[i..].iter().take_while(|c| **c == '"').count();
+    if quotes >= 3 {
+        // Raw strings end with as many quotes as they start with
+        i += quotes;
+        while i < chars.len() {
+            if chars[i..].iter().take_while(|c| **c == '"').count() >= quotes {
+                return Some(i + quotes);
+            }
+            i += 1;
+        }
+        return Some(chars.len());
+    }
+
+    i += 1;
+    while i < chars.len() {
+        let next = chars.get(i + 1).copied();
+        match chars[i] {
+            '"' if verbatim && next == Some('"') => i += 2,
+            '"' => return Some(i + 1),
+            '\\' if !verbatim => i += 2,
+            '\n' if !verbatim => return Some(i),
+            '{' if interpolated && next == Some('{') => i += 2,
+            '{' if interpolated => i = hole_end(chars, i + 1),
+            _ => i += 1,
+        }
+    }
+
+    Some(chars.len())
+}
+
+/// End of an interpolation hole, which can contain nested literals
+fn hole_end(chars: &[char], start: usize) -> usize {
+    let mut depth = 1;
+    let mut i = start;
+
+    while i < chars.len() {
+        match chars[i] {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return i + 1;
+                }
+            }
+            '"' | '\'' | '@' | '$' => {
+                if let Some(end) = literal_end(chars, i) {
+                    i = end;
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    chars.len()
+}
+
+/// Splits `IFoo<Bar, Baz>, IQux` into `IFoo` and `IQux`
+fn split_base_types(bases: &str) -> Vec<&str> {
+    let mut result = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+
+    for (i, c) in bases.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => depth -= 1,
+            ',' if depth == 0 => {
+                result.push(&bases[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    result.push(&bases[start..]);
+
+    result
+        .into_iter()
+        .map(|b| short_name(b.split('<').next().unwrap_or(b).trim()))
+        .filter(|b| !b.is_empty())
+        .collect()
+}
+
+/// Strips the namespace, e.g. `Altinn.App.Core.IDataProcessor` -> `IDataProcessor`
+fn short_name(name: &str) -> &str {
+    name.rsplit('.').next().unwrap_or(name)
+}
+
+pub fn report(apps: &[LocalApp]) -> Result<()> {
+    let mut extension_points = BTreeMap::<String, usize>::new();
+    let mut unregistered_apps = 0;
+    let mut failed = Vec::new();
+
+    for app in apps {
+        let code = match load(&app.dir) {
+            Ok(code) => code,
+            Err(err) => {
+                failed.push((app, err));
+                continue;
+            }
+        };
+
+        println!(
+            "{:<60} files={:<4} extensionPoints={:<3} registrations={}",
+            app.key(),
+            code.files,
+            code.implementations.len(),
+            code.registrations.len()
+        );
+
+        let mut unregistered = false;
+        for implementation in &code.implementations {
+            let registered = code.is_registered(implementation);
+            // AppBase is inherited, not registered in DI
+            unregistered |= !registered && implementation.extension_point != "AppBase";
+            println!(
+                "  {:<30} {:<40} registered={:<5} {}",
+                implementation.extension_point, implementation.class, registered, implementation.path
+            );
+        }
+        for registration in code.registrations.iter().filter(|r| r.path == PROGRAM_PATH) {
+            match &registration.implementation {
+                Some(implementation) => println!(
+                    "  {}<{}, {}>",
+                    registration.method, registration.service, implementation
+                ),
+                None => println!("  {}<{}>", registration.method, registration.service),
+            }
+        }
+
+        let used = code
+            .implementations
+            .iter()
+            .map(|i| i.extension_point.clone())
+            .collect::<BTreeSet<_>>();
+        for extension_point in used {
+            *extension_points.entry(extension_point).or_default() += 1;
+        }
+        unregistered_apps += usize::from(unregistered);
+    }
+
+    println!("--------------------------------------------------");
+    print_ranking("Extension points (number of apps)", &extension_points);
+    println!("Apps with implementations not registered in DI: {}", unregistered_apps);
+
+    println!("--------------------------------------------------");
+    println!("Failed to scan code ({}):", failed.len());
+    for (app, err) in &failed {
+        println!("  {}: {:#}", app.key(), err);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_comments() {
+        let code = "var a = 1; // comment\n/* block\ncomment */ var b = 2;";
+        assert_eq!(strip_comments(code), "var a = 1; \n\n  var b = 2;");
+    }
+
+    #[test]
+    fn keeps_comment_markers_in_literals() {
+        let code = r#"var types = new[] { "image/*", @"C:\temp\", $"{(x ? "a/*" : "b")}/*", '/', """raw "/*" """ };"#;
+        assert_eq!(strip_comments(code), code);
+    }
+
+    #[test]
+    fn finds_classes_after_comment_markers_in_strings() {
+        let code = r#"
+public static class ContentTypes
+{
+    public const string Images = "image/*";
+}
+
+public class DataProcessor : IDataProcessor
+{
+}
+
+// Registered in Program.cs, see */
+public class Validator : Altinn.App.Core.Features.IFormDataValidator
+{
+}
+"#;
+
+        let content = strip_comments(code);
+        let implementations = CLASS_REGEX
+            .captures_iter(&content)
+            .flat_map(|c| {
+                let class = c[1].to_string();
+                split_base_types(&c[2])
+                    .into_iter()
+                    .map(move |base| (class.clone(), base.to_string()))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            implementations,
+            vec![
+                ("DataProcessor".to_string(), "IDataProcessor".to_string()),
+                ("Validator".to_string(), "IFormDataValidator".to_string()),
+            ]
+        );
+    }
+}
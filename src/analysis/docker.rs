@@ -0,0 +1,378 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use chrono::{NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{print_histogram, print_ranking, AppFiles, LocalApp};
+
+pub const DOCKERFILE_PATH: &str = "Dockerfile";
+pub const HELM_VALUES_PATH: &str = "deployment/values.yaml";
+pub const HELM_CHART_PATH: &str = "deployment/Chart.yaml";
+
+const DOTNET_IMAGE_PREFIX: &str = "mcr.microsoft.com/dotnet/";
+
+/// End of support dates for .NET versions, https://dotnet.microsoft.com/en-us/platform/support/policy/dotnet-core
+const DOTNET_END_OF_SUPPORT: &[(&str, &str)] = &[
+    ("3.1", "2022-12-13"),
+    ("5.0", "2022-05-10"),
+    ("6.0", "2024-11-12"),
+    ("7.0", "2024-05-14"),
+    ("8.0", "2026-11-10"),
+    ("9.0", "2026-11-10"),
+    ("10.0", "2028-11-14"),
+];
+
+/// Steps that are part of the Dockerfile in the app template
+const TEMPLATE_STEPS: &[&str] = &[
+    "dotnet restore",
+    "dotnet publish",
+    "dotnet build",
+    "mkdir /tmp/logtelemetry",
+    "addgroup",
+    "adduser",
+    "chown",
+];
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppDocker {
+    pub stages: Vec<Stage>,
+    /// `RUN` instructions that are not part of the app template
+    pub custom_steps: Vec<String>,
+    pub helm: Option<Helm>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Stage {
+    pub image: String,
+    pub tag: Option<String>,
+    pub name: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Helm {
+    pub image_repository: Option<String>,
+    pub chart_version: Option<String>,
+}
+
+impl Stage {
+    /// .NET version of official images, e.g. `8.0` for `mcr.microsoft.com/dotnet/aspnet:8.0-alpine`
+    pub fn dotnet_version(&self) -> Option<&str> {
+        if !self.image.starts_with(DOTNET_IMAGE_PREFIX) {
+            return None;
+        }
+        let tag = self.tag.as_deref()?;
+        let end = tag.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(tag.len());
+        Some(&tag[..end]).filter(|v| !v.is_empty())
+    }
+
+    pub fn is_sdk(&self) -> bool {
+        self.image.ends_with("/sdk")
+    }
+}
+
+impl AppDocker {
+    /// The last stage is the one that is deployed
+    pub fn runtime(&self) -> Option<&Stage> {
+        self.stages.last()
+    }
+
+    pub fn sdk(&self) -> Option<&Stage> {
+        self.stages.iter().find(|s| s.is_sdk())
+    }
+
+    /// Images that are not official .NET images from Microsoft
+    pub fn non_standard_images(&self) -> Vec<&Stage> {
+        // Later stages can build on earlier ones by name
+        let names = self.stages.iter().filter_map(|s| s.name.as_deref()).collect::<Vec<_>>();
+        self.stages
+            .iter()
+            .filter(|s| !s.image.starts_with(DOTNET_IMAGE_PREFIX) && !names.contains(&s.image.as_str()))
+            .collect()
+    }
+}
+
+/// End of support date if the .NET version is out of support at the given date
+pub fn end_of_support(version: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let (_, date) = DOTNET_END_OF_SUPPORT.iter().find(|(v, _)| *v == version)?;
+    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+    (date <= today).then_some(date)
+}
+
+pub fn load(files: &(impl AppFiles + ?Sized)) -> Result<Option<AppDocker>> {
+    let Some(dockerfile) = files.read_file(DOCKERFILE_PATH)? else {
+        return Ok(None);
+    };
+
+    let mut result = parse_dockerfile(&dockerfile);
+
+    let values = files.read_file(HELM_VALUES_PATH)?;
+    let chart = files.read_file(HELM_CHART_PATH)?;
+    if values.is_some() || chart.is_some() {
+        result.helm = Some(Helm {
+            image_repository: values.as_deref().and_then(|v| yaml_value(v, "repository")),
+            chart_version: chart.as_deref().and_then(deployment_chart_version),
+        });
+    }
+
+    Ok(Some(result))
+}
+
+pub fn parse_dockerfile(content: &str) -> AppDocker {
+    let mut result = AppDocker::default();
+
+    // Instructions can span several lines using `\` at the end of a line
+    let content = content.replace("\\\r\n", " ").replace("\\\n", " ");
+
+    for line in content.lines().map(|l| l.trim()) {
+        let Some((instruction, args)) = line.split_once(char::is_whitespace) else {
+            continue;
+        };
+        let args = args.trim();
+
+        match instruction.to_ascii_uppercase().as_str() {
+            "FROM" => {
+                let mut parts = args.split_whitespace().filter(|p| !p.starts_with("--"));
+                let reference = parts.next().unwrap_or_default();
+                let name = match (parts.next(), parts.next()) {
+                    (Some(as_), Some(name)) if as_.eq_ignore_ascii_case("as") => Some(name.to_string()),
+                    _ => None,
+                };
+                let (image, tag) = match reference.rsplit_once(':') {
+                    Some((image, tag)) if !tag.contains('/') => (image, Some(tag.to_string())),
+                    _ => (reference, None),
+                };
+                result.stages.push(Stage {
+                    image: image.to_string(),
+                    tag,
+                    name,
+                });
+            }
+            "RUN" => {
+                let standard = args
+                    .split("&&")
+                    .all(|step| TEMPLATE_STEPS.iter().any(|t| step.trim().starts_with(t)));
+                if !standard {
+                    result.custom_steps.push(args.to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    result
+}
+
+/// First value of a key in a YAML document, which is good enough for the flat helm values of apps
+fn yaml_value(content: &str, key: &str) -> Option<String> {
+    content.lines().find_map(|line| {
+        let (k, v) = line.trim().split_once(':')?;
+        (k.trim() == key).then(|| v.trim().trim_matches('"').trim_matches('\'').to_string())
+    })
+}
+
+/// Version of the `deployment` chart dependency in Chart.yaml
+fn deployment_chart_version(content: &str) -> Option<String> {
+    let mut in_deployment = false;
+
+    for line in content.lines() {
+        let line = line.trim().trim_start_matches("- ").trim();
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim().trim_matches('"').trim_matches('\'');
+        match key.trim() {
+            "name" => in_deployment = value == "deployment",
+            "version" if in_deployment => return Some(value.to_string()),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+pub fn report(apps: &[LocalApp]) -> Result<()> {
+    let mut runtimes = BTreeMap::<String, usize>::new();
+    let mut sdks = BTreeMap::<String, usize>::new();
+    let mut charts = BTreeMap::<String, usize>::new();
+    let mut warnings = BTreeMap::<String, usize>::new();
+    let mut missing = Vec::new();
+    let mut failed = Vec::new();
+    let today = Utc::now().date_naive();
+
+    for app in apps {
+        let docker = match load(&app.dir) {
+            Ok(Some(docker)) => docker,
+            Ok(None) => {
+                missing.push(app);
+                continue;
+            }
+            Err(err) => {
+                failed.push((app, err));
+                continue;
+            }
+        };
+
+        let image = |stage: Option<&Stage>| {
+            stage.map_or("-".to_string(), |s| {
+                format!("{}:{}", s.image, s.tag.as_deref().unwrap_or("latest"))
+            })
+        };
+        let chart_version = docker.helm.as_ref().and_then(|h| h.chart_version.as_deref());
+        println!(
+            "{:<60} runtime={:<50} sdk={:<50} chart={}",
+            app.key(),
+            image(docker.runtime()),
+            image(docker.sdk()),
+            chart_version.unwrap_or("-")
+        );
+
+        let mut app_warnings = Vec::new();
+        for (kind, stage) in [("runtime", docker.runtime()), ("sdk", docker.sdk())] {
+            let Some(version) = stage.and_then(|s| s.dotnet_version()) else {
+                continue;
+            };
+            match kind {
+                "runtime" => *runtimes.entry(version.to_string()).or_default() += 1,
+                _ => *sdks.entry(version.to_string()).or_default() += 1,
+            }
+            if let Some(date) = end_of_support(version, today) {
+                app_warnings.push(format!(".NET {} {} is out of support since {}", version, kind, date));
+                *warnings.entry(format!("end-of-life {}", kind)).or_default() += 1;
+            }
+        }
+        let non_standard = docker.non_standard_images();
+        for stage in &non_standard {
+            app_warnings.push(format!("non-standard image {}", image(Some(stage))));
+        }
+        if !non_standard.is_empty() {
+            *warnings.entry("non-standard image".to_string()).or_default() += 1;
+        }
+        for step in &docker.custom_steps {
+            app_warnings.push(format!("custom build step: RUN {}", step));
+        }
+        if !docker.custom_steps.is_empty() {
+            *warnings.entry("custom build steps".to_string()).or_default() += 1;
+        }
+        for warning in app_warnings {
+            println!("  WARNING: {}", warning);
+        }
+
+        *charts.entry(chart_version.unwrap_or("<none>").to_string()).or_default() += 1;
+    }
+
+    println!("--------------------------------------------------");
+    print_histogram(".NET runtime version", &runtimes);
+    print_histogram(".NET SDK version", &sdks);
+    print_histogram("Deployment chart version", &charts);
+    print_ranking("Warnings (number of apps)", &warnings);
+
+    println!("--------------------------------------------------");
+    println!("Missing {} ({}):", DOCKERFILE_PATH, missing.len());
+    for app in &missing {
+        println!("  {}", app.key());
+    }
+
+    println!("Failed to read {} ({}):", DOCKERFILE_PATH, failed.len());
+    for (app, err) in &failed {
+        println!("  {}: {:#}", app.key(), err);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stage(image: &str, tag: Option<&str>, name: Option<&str>) -> Stage {
+        Stage {
+            image: image.to_string(),
+            tag: tag.map(|t| t.to_string()),
+            name: name.map(|n| n.to_string()),
+        }
+    }
+
+    #[test]
+    fn parses_stages() {
+        let docker = parse_dockerfile(
+            r#"
+            FROM --platform=$BUILDPLATFORM mcr.microsoft.com/dotnet/sdk:8.0-alpine AS build
+            WORKDIR /App
+            FROM registry.example.com:5000/base/aspnet:8.0 as base
+            FROM registry.example.com:5000/base/tools
+            from mcr.microsoft.com/dotnet/aspnet:6.0-alpine AS final
+            "#,
+        );
+
+        assert_eq!(
+            docker.stages,
+            vec![
+                stage("mcr.microsoft.com/dotnet/sdk", Some("8.0-alpine"), Some("build")),
+                stage("registry.example.com:5000/base/aspnet", Some("8.0"), Some("base")),
+                stage("registry.example.com:5000/base/tools", None, None),
+                stage("mcr.microsoft.com/dotnet/aspnet", Some("6.0-alpine"), Some("final")),
+            ]
+        );
+        assert_eq!(docker.sdk().and_then(|s| s.dotnet_version()), Some("8.0"));
+        assert_eq!(docker.runtime().and_then(|s| s.dotnet_version()), Some("6.0"));
+        assert_eq!(docker.non_standard_images().len(), 2);
+    }
+
+    #[test]
+    fn stages_can_build_on_earlier_stages() {
+        let docker = parse_dockerfile(
+            "FROM mcr.microsoft.com/dotnet/sdk:8.0 AS build\nFROM build AS publish\nFROM mcr.microsoft.com/dotnet/aspnet:8.0",
+        );
+
+        assert!(docker.non_standard_images().is_empty());
+    }
+
+    #[test]
+    fn only_reports_run_steps_that_are_not_in_the_template() {
+        let docker = parse_dockerfile(
+            r#"
+            FROM mcr.microsoft.com/dotnet/sdk:8.0-alpine AS build
+            RUN dotnet restore App/App.csproj
+            RUN dotnet build App.csproj -c Release -o /app_output \
+                && dotnet publish App.csproj -c Release -o /app_output
+            RUN mkdir /tmp/logtelemetry && addgroup -g 3000 dotnet && adduser -u 1000 -G dotnet -D -s /bin/false dotnet
+            RUN apk add --no-cache icu-libs
+            RUN dotnet restore && npm ci
+            "#,
+        );
+
+        assert_eq!(
+            docker.custom_steps,
+            vec!["apk add --no-cache icu-libs", "dotnet restore && npm ci"]
+        );
+    }
+
+    #[test]
+    fn finds_the_deployment_chart_version() {
+        let chart = r#"
+apiVersion: v1
+name: app
+version: 1.1.0
+dependencies:
+  - name: deployment
+    repository: https://charts.altinn.studio/
+    version: "3.1.0"
+  - name: other
+    version: 9.9.9
+"#;
+
+        assert_eq!(deployment_chart_version(chart).as_deref(), Some("3.1.0"));
+        assert_eq!(deployment_chart_version("name: app\nversion: 1.0.0"), None);
+    }
+
+    #[test]
+    fn reports_end_of_support_from_the_given_date() {
+        let date = |d: &str| NaiveDate::parse_from_str(d, "%Y-%m-%d").unwrap();
+
+        assert_eq!(end_of_support("6.0", date("2024-11-12")), Some(date("2024-11-12")));
+        assert_eq!(end_of_support("6.0", date("2024-11-11")), None);
+        assert_eq!(end_of_support("8.0", date("2025-01-01")), None);
+        assert_eq!(end_of_support("4.0", date("2030-01-01")), None);
+    }
+}
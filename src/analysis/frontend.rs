@@ -0,0 +1,196 @@
+use std::collections::BTreeMap;
+use std::sync::LazyLock;
+
+use anyhow::Result;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::{print_histogram, AppFiles, LocalApp};
+
+pub const PATH: &str = "App/views/Home/Index.cshtml";
+
+static ASSET_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)(?:src|href)\s*=\s*["']([^"']*altinn-app-frontend[^"']*\.(?:js|css))["']"#).unwrap()
+});
+static CDN_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(?:https?:)?//altinncdn\.no/toolkits/altinn-app-frontend/([^/]+)/").unwrap());
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value", rename_all = "camelCase")]
+pub enum FrontendVersion {
+    /// Follows the latest release of a major version from the CDN, e.g. `4`
+    Major(String),
+    /// Locked to a specific release from the CDN, e.g. `4.5.2`
+    Pinned(String),
+    /// Assets served from somewhere other than the CDN
+    Custom(String),
+}
+
+impl FrontendVersion {
+    pub fn major(&self) -> Option<&str> {
+        match self {
+            FrontendVersion::Major(v) | FrontendVersion::Pinned(v) => v.split('.').next(),
+            FrontendVersion::Custom(_) => None,
+        }
+    }
+}
+
+pub enum FrontendStatus {
+    Referenced(FrontendVersion),
+    /// The view exists, but doesn't reference the frontend
+    Unreferenced,
+    Missing,
+}
+
+/// Finds the frontend version referenced by the app's view
+pub fn load(files: &(impl AppFiles + ?Sized)) -> Result<FrontendStatus> {
+    let Some(content) = files.read_file(PATH)? else {
+        return Ok(FrontendStatus::Missing);
+    };
+
+    Ok(match parse(&content) {
+        Some(version) => FrontendStatus::Referenced(version),
+        None => FrontendStatus::Unreferenced,
+    })
+}
+
+pub fn parse(content: &str) -> Option<FrontendVersion> {
+    let urls = ASSET_REGEX
+        .captures_iter(content)
+        .map(|c| c.get(1).unwrap().as_str())
+        .collect::<Vec<_>>();
+
+    // The script decides which version actually runs, the stylesheet is only a fallback
+    let url = urls.iter().find(|url| url.ends_with(".js")).or_else(|| urls.first())?;
+
+    let Some(captures) = CDN_REGEX.captures(url) else {
+        return Some(FrontendVersion::Custom(url.to_string()));
+    };
+
+    let version = captures.get(1).unwrap().as_str().to_string();
+    if version.chars().all(|c| c.is_ascii_digit()) {
+        Some(FrontendVersion::Major(version))
+    } else {
+        Some(FrontendVersion::Pinned(version))
+    }
+}
+
+pub fn report(apps: &[LocalApp]) -> Result<()> {
+    let mut versions = BTreeMap::<String, usize>::new();
+    let mut majors = BTreeMap::<String, usize>::new();
+    let mut missing = Vec::new();
+    let mut unreferenced = Vec::new();
+    let mut failed = Vec::new();
+
+    for app in apps {
+        let version = match load(&app.dir) {
+            Ok(FrontendStatus::Referenced(version)) => version,
+            Ok(FrontendStatus::Unreferenced) => {
+                unreferenced.push(app);
+                continue;
+            }
+            Ok(FrontendStatus::Missing) => {
+                missing.push(app);
+                continue;
+            }
+            Err(err) => {
+                failed.push((app, err));
+                continue;
+            }
+        };
+
+        let (kind, value) = match &version {
+            FrontendVersion::Major(v) => ("major", v),
+            FrontendVersion::Pinned(v) => ("pinned", v),
+            FrontendVersion::Custom(url) => ("custom", url),
+        };
+        println!("{:<60} {:<8} {}", app.key(), kind, value);
+
+        let key = match &version {
+            FrontendVersion::Custom(_) => "custom".to_string(),
+            _ => format!("{} ({})", value, kind),
+        };
+        *versions.entry(key).or_default() += 1;
+        *majors
+            .entry(version.major().unwrap_or("custom").to_string())
+            .or_default() += 1;
+    }
+
+    println!("--------------------------------------------------");
+    print_histogram("Frontend version", &versions);
+    print_histogram("Frontend major version", &majors);
+
+    println!("--------------------------------------------------");
+    println!("No {} ({}):", PATH, missing.len());
+    for app in &missing {
+        println!("  {}", app.key());
+    }
+
+    println!("No frontend reference in {} ({}):", PATH, unreferenced.len());
+    for app in &unreferenced {
+        println!("  {}", app.key());
+    }
+
+    println!("Failed to read {} ({}):", PATH, failed.len());
+    for (app, err) in &failed {
+        println!("  {}: {:#}", app.key(), err);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::analysis::MemoryFiles;
+
+    #[test]
+    fn parses_major_versions() {
+        let view = r#"
+            <link rel="stylesheet" type="text/css" href="https://altinncdn.no/toolkits/altinn-app-frontend/4/altinn-app-frontend.css">
+            <script src="https://altinncdn.no/toolkits/altinn-app-frontend/4/altinn-app-frontend.js"></script>
+        "#;
+
+        let version = parse(view).unwrap();
+
+        assert_eq!(version, FrontendVersion::Major("4".to_string()));
+        assert_eq!(version.major(), Some("4"));
+    }
+
+    #[test]
+    fn parses_pinned_versions_from_the_script() {
+        let view = r#"
+            <link href="//altinncdn.no/toolkits/altinn-app-frontend/4/altinn-app-frontend.css" rel="stylesheet">
+            <script src='https://altinncdn.no/toolkits/altinn-app-frontend/4.5.2/altinn-app-frontend.js'></script>
+        "#;
+
+        let version = parse(view).unwrap();
+
+        assert_eq!(version, FrontendVersion::Pinned("4.5.2".to_string()));
+        assert_eq!(version.major(), Some("4"));
+    }
+
+    #[test]
+    fn parses_custom_urls() {
+        let view = r#"<script src="/ttd/app/wwwroot/altinn-app-frontend.js"></script>"#;
+
+        let version = parse(view).unwrap();
+
+        assert_eq!(
+            version,
+            FrontendVersion::Custom("/ttd/app/wwwroot/altinn-app-frontend.js".to_string())
+        );
+        assert_eq!(version.major(), None);
+    }
+
+    #[test]
+    fn tells_a_missing_view_from_one_without_a_reference() {
+        let missing = MemoryFiles(HashMap::new());
+        let unreferenced = MemoryFiles(HashMap::from([(PATH, "<html><body></body></html>")]));
+
+        assert!(matches!(load(&missing).unwrap(), FrontendStatus::Missing));
+        assert!(matches!(load(&unreferenced).unwrap(), FrontendStatus::Unreferenced));
+    }
+}
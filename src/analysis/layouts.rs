@@ -0,0 +1,315 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::{print_ranking, AppFiles, LocalApp};
+
+pub const UI_DIR: &str = "App/ui";
+pub const LAYOUT_SETS_PATH: &str = "App/ui/layout-sets.json";
+
+/// All layouts of an app. Apps without `layout-sets.json` get a single set without an id
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppLayouts {
+    pub sets: Vec<LayoutSet>,
+    /// Files that couldn't be parsed, so that one broken page doesn't hide the rest of the app
+    pub errors: Vec<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LayoutSet {
+    pub id: Option<String>,
+    pub data_type: Option<String>,
+    pub tasks: Vec<String>,
+    pub settings: Option<LayoutSettings>,
+    pub pages: Vec<Page>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Page {
+    pub name: String,
+    pub path: String,
+    pub components: Vec<Component>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Component {
+    #[serde(default)]
+    pub id: String,
+    #[serde(rename = "type", default)]
+    pub component_type: String,
+    #[serde(flatten)]
+    pub properties: Map<String, Value>,
+}
+
+impl Component {
+    /// Looks up a property, where `.` separates nested properties, e.g. `textResourceBindings.title`
+    pub fn property(&self, path: &str) -> Option<&Value> {
+        let mut parts = path.split('.');
+        let mut value = self.properties.get(parts.next()?)?;
+        for part in parts {
+            value = value.as_object()?.get(part)?;
+        }
+        Some(value)
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LayoutSets {
+    #[serde(default)]
+    pub sets: Vec<LayoutSetDefinition>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LayoutSetDefinition {
+    pub id: String,
+    pub data_type: Option<String>,
+    #[serde(default)]
+    pub tasks: Vec<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LayoutSettings {
+    pub pages: Option<PagesSettings>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PagesSettings {
+    #[serde(default)]
+    pub order: Vec<String>,
+    pub exclude_from_pdf: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+struct PageFile {
+    data: PageData,
+}
+
+#[derive(Deserialize)]
+struct PageData {
+    #[serde(default)]
+    layout: Vec<Component>,
+}
+
+impl AppLayouts {
+    pub fn components(&self) -> impl Iterator<Item = (&LayoutSet, &Page, &Component)> {
+        self.sets.iter().flat_map(|set| {
+            set.pages
+                .iter()
+                .flat_map(move |page| page.components.iter().map(move |c| (set, page, c)))
+        })
+    }
+}
+
+pub fn load(files: &(impl AppFiles + ?Sized)) -> Result<AppLayouts> {
+    let mut result = AppLayouts::default();
+
+    let definitions = match files.read_file(LAYOUT_SETS_PATH)? {
+        Some(content) => match serde_json::from_str::<LayoutSets>(&content) {
+            Ok(layout_sets) => Some(layout_sets.sets),
+            Err(e) => {
+                result.errors.push(format!("{}: {}", LAYOUT_SETS_PATH, e));
+                None
+            }
+        },
+        None => None,
+    };
+
+    match definitions {
+        Some(definitions) => {
+            for definition in definitions {
+                let dir = format!("{}/{}", UI_DIR, definition.id);
+                let mut set = load_set(files, &dir, &mut result.errors)?;
+                set.id = Some(definition.id);
+                set.data_type = definition.data_type;
+                set.tasks = definition.tasks;
+                result.sets.push(set);
+            }
+        }
+        None => {
+            let set = load_set(files, UI_DIR, &mut result.errors)?;
+            if !set.pages.is_empty() || set.settings.is_some() {
+                result.sets.push(set);
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+fn load_set(files: &(impl AppFiles + ?Sized), dir: &str, errors: &mut Vec<String>) -> Result<LayoutSet> {
+    let mut set = LayoutSet::default();
+
+    let settings_path = format!("{}/Settings.json", dir);
+    if let Some(content) = files.read_file(&settings_path)? {
+        match serde_json::from_str::<LayoutSettings>(&content) {
+            Ok(settings) => set.settings = Some(settings),
+            Err(e) => errors.push(format!("{}: {}", settings_path, e)),
+        }
+    }
+
+    let mut paths = files.list_files(&format!("{}/layouts", dir))?;
+    // Apps created before multiple pages were supported only have a single layout file
+    let legacy_path = format!("{}/FormLayout.json", dir);
+    if paths.is_empty() && files.list_files(dir)?.contains(&legacy_path) {
+        paths.push(legacy_path);
+    }
+
+    for path in paths.into_iter().filter(|p| p.ends_with(".json")) {
+        let Some(content) = files.read_file(&path)? else {
+            continue;
+        };
+        let page = match serde_json::from_str::<PageFile>(&content) {
+            Ok(page) => page,
+            Err(e) => {
+                errors.push(format!("{}: {}", path, e));
+                continue;
+            }
+        };
+
+        let name = path
+            .rsplit('/')
+            .next()
+            .and_then(|n| n.strip_suffix(".json"))
+            .unwrap_or(&path)
+            .to_string();
+        set.pages.push(Page {
+            name,
+            path,
+            components: page.data.layout,
+        });
+    }
+
+    // Present pages in the order the app shows them
+    if let Some(order) = set.settings.as_ref().and_then(|s| s.pages.as_ref()).map(|p| &p.order) {
+        set.pages
+            .sort_by_key(|page| order.iter().position(|n| n == &page.name).unwrap_or(usize::MAX));
+    }
+
+    Ok(set)
+}
+
+/// Properties that are parsed into their own fields, so they can't be filtered on
+const COMPONENT_FIELDS: [&str; 2] = ["id", "type"];
+
+pub fn report(apps: &[LocalApp], component: Option<&str>, property: Option<&str>) -> Result<()> {
+    if let Some(property) = property.filter(|p| COMPONENT_FIELDS.contains(p)) {
+        bail!(
+            "All components have `{}`, use --component to filter on component type",
+            property
+        );
+    }
+    let filtered = component.is_some() || property.is_some();
+
+    let mut type_components = BTreeMap::<String, usize>::new();
+    let mut type_apps = BTreeMap::<String, usize>::new();
+    let mut property_components = BTreeMap::<String, usize>::new();
+    // Keyed by `Type.property`, e.g. `Input.readOnly`
+    let mut type_properties = BTreeMap::<String, usize>::new();
+    let mut matching_apps = 0;
+    let mut failed = Vec::new();
+
+    for app in apps {
+        let layouts = match load(&app.dir) {
+            Ok(layouts) => layouts,
+            Err(err) => {
+                failed.push((app, format!("{:#}", err)));
+                continue;
+            }
+        };
+        for err in &layouts.errors {
+            failed.push((app, err.clone()));
+        }
+
+        let mut app_types = BTreeSet::<String>::new();
+        let mut matches = Vec::new();
+
+        for (set, page, c) in layouts.components() {
+            *type_components.entry(c.component_type.clone()).or_default() += 1;
+            app_types.insert(c.component_type.clone());
+            for key in c.properties.keys() {
+                *type_properties
+                    .entry(format!("{}.{}", c.component_type, key))
+                    .or_default() += 1;
+            }
+
+            if !filtered {
+                continue;
+            }
+            if component.is_some_and(|t| !t.eq_ignore_ascii_case(&c.component_type)) {
+                continue;
+            }
+            let value = match property {
+                Some(property) => match c.property(property) {
+                    Some(value) => Some(value),
+                    None => continue,
+                },
+                None => None,
+            };
+
+            for key in c.properties.keys() {
+                *property_components.entry(key.clone()).or_default() += 1;
+            }
+
+            let location = match &set.id {
+                Some(id) => format!("{}/{}", id, page.name),
+                None => page.name.clone(),
+            };
+            matches.push((location, c, value));
+        }
+
+        for t in app_types {
+            *type_apps.entry(t).or_default() += 1;
+        }
+
+        if !filtered {
+            let pages = layouts.sets.iter().map(|s| s.pages.len()).sum::<usize>();
+            let components = layouts.components().count();
+            println!(
+                "{:<60} sets={:<3} pages={:<4} components={}",
+                app.key(),
+                layouts.sets.iter().filter(|s| s.id.is_some()).count(),
+                pages,
+                components
+            );
+            continue;
+        }
+
+        if matches.is_empty() {
+            continue;
+        }
+
+        matching_apps += 1;
+        println!("{} ({} matches)", app.key(), matches.len());
+        for (location, c, value) in matches {
+            match value {
+                Some(value) => println!("  {:<50} {:<20} {}={}", location, c.id, property.unwrap_or(""), value),
+                None => println!("  {:<50} {:<20} {}", location, c.id, c.component_type),
+            }
+        }
+    }
+
+    println!("--------------------------------------------------");
+    if filtered {
+        print_ranking("Properties of matching components", &property_components);
+        println!("--------------------------------------------------");
+        println!("Apps with matching components: {} of {}", matching_apps, apps.len());
+    } else {
+        print_ranking("Component types (number of apps)", &type_apps);
+        print_ranking("Component types (number of components)", &type_components);
+        print_ranking("Component properties (number of components)", &type_properties);
+    }
+
+    println!("--------------------------------------------------");
+    println!("Failed to parse layouts ({}):", failed.len());
+    for (app, err) in &failed {
+        println!("  {}: {}", app.key(), err);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,86 @@
+use anyhow::Result;
+
+use super::{AppFiles, LocalApp};
+use crate::gitea_client::ApplicationMetadata;
+
+pub const PATH: &str = "App/config/applicationmetadata.json";
+
+pub enum MetadataStatus {
+    Parsed(Box<ApplicationMetadata>),
+    Missing,
+    Malformed(String),
+}
+
+pub fn load(files: &(impl AppFiles + ?Sized)) -> MetadataStatus {
+    let content = match files.read_file(PATH) {
+        Ok(Some(content)) => content,
+        Ok(None) => return MetadataStatus::Missing,
+        Err(e) => return MetadataStatus::Malformed(format!("{:#}", e)),
+    };
+
+    match serde_json::from_str::<ApplicationMetadata>(&content) {
+        Ok(metadata) => MetadataStatus::Parsed(Box::new(metadata)),
+        Err(e) => MetadataStatus::Malformed(e.to_string()),
+    }
+}
+
+pub fn report(apps: &[LocalApp]) -> Result<()> {
+    let mut missing = Vec::new();
+    let mut malformed = Vec::new();
+    let mut parsed = 0;
+
+    for app in apps {
+        let metadata = match load(&app.dir) {
+            MetadataStatus::Parsed(metadata) => metadata,
+            MetadataStatus::Missing => {
+                missing.push(app);
+                continue;
+            }
+            MetadataStatus::Malformed(err) => {
+                malformed.push((app, err));
+                continue;
+            }
+        };
+
+        parsed += 1;
+
+        let title = metadata
+            .title
+            .get("nb")
+            .or_else(|| metadata.title.values().next())
+            .map(|t| t.as_str())
+            .unwrap_or("");
+        let party_types = metadata.party_types_allowed.allowed();
+        let on_entry = metadata.on_entry.as_ref().and_then(|e| e.show.as_deref()).unwrap_or("");
+
+        println!(
+            "{:<60} dataTypes={:<3} partyTypes={:<40} autoDelete={:<5} onEntry={:<15} {}",
+            app.key(),
+            metadata.data_types.len(),
+            if party_types.is_empty() {
+                "all".to_string()
+            } else {
+                party_types.join(",")
+            },
+            metadata.auto_delete_on_process_end,
+            on_entry,
+            title
+        );
+    }
+
+    println!("--------------------------------------------------");
+    println!("Missing {} ({}):", PATH, missing.len());
+    for app in &missing {
+        println!("  {}", app.key());
+    }
+
+    println!("Malformed {} ({}):", PATH, malformed.len());
+    for (app, err) in &malformed {
+        println!("  {}: {}", app.key(), err);
+    }
+
+    println!("--------------------------------------------------");
+    println!("Parsed metadata for {} of {} repos", parsed, apps.len());
+
+    Ok(())
+}
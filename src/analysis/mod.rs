@@ -0,0 +1,334 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use quick_xml::events::BytesStart;
+use reqwest::Url;
+use tokio::task::spawn_blocking;
+
+use crate::configuration::{Analysis, Configuration};
+use crate::deployment::{self, Deployment};
+use crate::gitea_client::GiteaRepo;
+
+pub mod analyzer;
+pub mod code;
+pub mod docker;
+pub mod frontend;
+pub mod layouts;
+pub mod metadata;
+pub mod models;
+pub mod options;
+pub mod packages;
+pub mod policy;
+pub mod process;
+pub mod remote;
+pub mod runner;
+pub mod texts;
+
+/// An app repo that has been cloned into `<dir>/<org>/<name>`
+#[derive(Debug, Clone)]
+pub struct LocalApp {
+    pub org: String,
+    pub name: String,
+    pub dir: PathBuf,
+}
+
+impl LocalApp {
+    pub fn key(&self) -> String {
+        format!("{}/{}", self.org, self.name)
+    }
+
+    /// The repo as it would be returned from Gitea, for when Gitea isn't or can't be asked
+    pub fn gitea_repo(&self, base_url: &Url) -> GiteaRepo {
+        GiteaRepo {
+            clone_url: format!("{}repos/{}/{}.git", base_url, self.org, self.name),
+            name: Some(self.name.clone()),
+            full_name: Some(self.key()),
+            ..Default::default()
+        }
+    }
+}
+
+pub async fn run(config: &'static Configuration, analysis: &Analysis, deployed: &[Deployment]) -> Result<()> {
+    if let Analysis::Report {
+        analyzers,
+        orgs,
+        output,
+        offline,
+        remote,
+    } = analysis
+    {
+        return runner::run(config, analyzers, orgs, output.as_deref(), *offline, *remote, deployed).await;
+    }
+
+    let dir = config.dir.clone();
+    let apps = spawn_blocking(move || discover(&dir))
+        .await
+        .context("Failed to wait for discovery")??;
+    let apps = deployment::filter_apps(config, apps, deployed).await?;
+
+    let analysis = analysis.clone();
+
+    spawn_blocking(move || {
+        println!("Analyzing: {}", config.dir.display());
+        println!("Number of repos: {}", apps.len());
+        println!("--------------------------------------------------");
+
+        match analysis {
+            Analysis::Metadata => metadata::report(&apps),
+            Analysis::Packages => packages::report(&apps),
+            Analysis::Frontend => frontend::report(&apps),
+            Analysis::Layouts { component, property } => {
+                layouts::report(&apps, component.as_deref(), property.as_deref())
+            }
+            Analysis::Process => process::report(&apps),
+            Analysis::Policy => policy::report(&apps),
+            Analysis::Texts => texts::report(&apps),
+            Analysis::Models => models::report(&apps),
+            Analysis::Code => code::report(&apps),
+            Analysis::Options => options::report(&apps),
+            Analysis::Docker => docker::report(&apps),
+            Analysis::Report { .. } => unreachable!("Reports run asynchronously"),
+        }
+    })
+    .await
+    .context("Failed to wait for analysis")?
+}
+
+/// Read access to the files of an app, either in a local clone or fetched from Gitea.
+/// All paths are relative to the root of the repo and use `/` as separator
+pub trait AppFiles {
+    /// Reads a file, returning `None` if it doesn't exist
+    fn read_file(&self, path: &str) -> Result<Option<String>>;
+
+    /// Lists the files directly in a dir, returning an empty list if the dir doesn't exist
+    fn list_files(&self, dir: &str) -> Result<Vec<String>>;
+
+    /// Recursively finds files matching the predicate
+    fn find_files(&self, predicate: &dyn Fn(&str) -> bool) -> Result<Vec<String>>;
+}
+
+/// The app dir of a local clone
+impl AppFiles for Path {
+    fn read_file(&self, path: &str) -> Result<Option<String>> {
+        read_file(self, path)
+    }
+
+    fn list_files(&self, dir: &str) -> Result<Vec<String>> {
+        list_files(self, dir)
+    }
+
+    fn find_files(&self, predicate: &dyn Fn(&str) -> bool) -> Result<Vec<String>> {
+        find_files(self, predicate)
+    }
+}
+
+/// Files kept in memory, keyed by path, for testing the parsers without an app dir
+#[cfg(test)]
+pub struct MemoryFiles(pub std::collections::HashMap<&'static str, &'static str>);
+
+#[cfg(test)]
+impl AppFiles for MemoryFiles {
+    fn read_file(&self, path: &str) -> Result<Option<String>> {
+        Ok(self.0.get(path).map(|c| c.to_string()))
+    }
+
+    fn list_files(&self, dir: &str) -> Result<Vec<String>> {
+        let prefix = format!("{}/", dir);
+        let mut result = self
+            .0
+            .keys()
+            .filter(|p| p.strip_prefix(&prefix).is_some_and(|name| !name.contains('/')))
+            .map(|p| p.to_string())
+            .collect::<Vec<_>>();
+        result.sort();
+        Ok(result)
+    }
+
+    fn find_files(&self, predicate: &dyn Fn(&str) -> bool) -> Result<Vec<String>> {
+        let mut result = self
+            .0
+            .keys()
+            .filter(|p| predicate(p))
+            .map(|p| p.to_string())
+            .collect::<Vec<_>>();
+        result.sort();
+        Ok(result)
+    }
+}
+
+impl AppFiles for PathBuf {
+    fn read_file(&self, path: &str) -> Result<Option<String>> {
+        self.as_path().read_file(path)
+    }
+
+    fn list_files(&self, dir: &str) -> Result<Vec<String>> {
+        self.as_path().list_files(dir)
+    }
+
+    fn find_files(&self, predicate: &dyn Fn(&str) -> bool) -> Result<Vec<String>> {
+        self.as_path().find_files(predicate)
+    }
+}
+
+/// Finds all cloned repos, sorted by org and name
+pub fn discover(dir: &Path) -> Result<Vec<LocalApp>> {
+    let mut result = Vec::new();
+
+    for org in read_dirs(dir).context("Failed to read directory")? {
+        for repo in read_dirs(&org).context("Failed to read org directory")? {
+            let (Some(org), Some(name)) = (file_name(&org), file_name(&repo)) else {
+                continue;
+            };
+
+            result.push(LocalApp { org, name, dir: repo });
+        }
+    }
+
+    result.sort_by(|a, b| a.org.cmp(&b.org).then_with(|| a.name.cmp(&b.name)));
+
+    Ok(result)
+}
+
+/// Reads a file relative to the app dir, returning `None` if it doesn't exist
+pub fn read_file(app_dir: &Path, path: &str) -> Result<Option<String>> {
+    match fs::read_to_string(app_dir.join(path)) {
+        // Some files are saved with a BOM by Windows editors
+        Ok(content) => Ok(Some(content.trim_start_matches('\u{feff}').to_string())),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {}", path)),
+    }
+}
+
+/// Lists the files directly in a dir relative to the app dir, returning an empty list if the dir doesn't exist.
+/// Returned paths are relative to the app dir
+pub fn list_files(app_dir: &Path, dir: &str) -> Result<Vec<String>> {
+    let entries = match fs::read_dir(app_dir.join(dir)) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", dir)),
+    };
+
+    let mut result = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        if !entry.file_type()?.is_file() {
+            continue;
+        }
+        if let Some(name) = file_name(&entry.path()) {
+            result.push(format!("{}/{}", dir, name));
+        }
+    }
+
+    result.sort();
+
+    Ok(result)
+}
+
+/// Recursively finds files in the app dir matching the predicate, skipping VCS and build output dirs.
+/// Returned paths are relative to the app dir and use `/` as separator
+pub fn find_files(app_dir: &Path, predicate: impl Fn(&str) -> bool) -> Result<Vec<String>> {
+    const SKIP_DIRS: [&str; 5] = [".git", "bin", "obj", "node_modules", ".vs"];
+
+    let mut result = Vec::new();
+    let mut stack = vec![app_dir.to_path_buf()];
+
+    while let Some(dir) = stack.pop() {
+        for entry in fs::read_dir(&dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+            let entry = entry?;
+            let path = entry.path();
+            let Some(name) = file_name(&path) else {
+                continue;
+            };
+
+            if entry.file_type()?.is_dir() {
+                if !SKIP_DIRS.contains(&name.as_str()) {
+                    stack.push(path);
+                }
+                continue;
+            }
+
+            let Ok(relative) = path.strip_prefix(app_dir) else {
+                continue;
+            };
+            let relative = relative.to_string_lossy().replace('\\', "/");
+            if predicate(&relative) {
+                result.push(relative);
+            }
+        }
+    }
+
+    result.sort();
+
+    Ok(result)
+}
+
+/// Gets an attribute of an XML element by its local name, ignoring the namespace prefix
+pub fn xml_attribute(e: &BytesStart, name: &str) -> Result<Option<String>> {
+    for attr in e.attributes() {
+        let attr = attr?;
+        if attr.key.local_name().as_ref() == name.as_bytes() {
+            return Ok(Some(attr.unescape_value()?.to_string()));
+        }
+    }
+    Ok(None)
+}
+
+/// Compares version strings by their numeric components, so that `8.10.0` > `8.9.0`
+pub fn compare_versions(a: &str, b: &str) -> std::cmp::Ordering {
+    fn parts(v: &str) -> Vec<u64> {
+        v.split(|c: char| !c.is_ascii_digit())
+            .filter(|p| !p.is_empty())
+            .filter_map(|p| p.parse().ok())
+            .collect()
+    }
+
+    parts(a).cmp(&parts(b)).then_with(|| a.cmp(b))
+}
+
+/// Prints counts ordered by version, newest first
+pub fn print_histogram(title: &str, histogram: &BTreeMap<String, usize>) {
+    let mut entries = histogram.iter().collect::<Vec<_>>();
+    entries.sort_by(|(a, _), (b, _)| compare_versions(b, a));
+
+    print_bars(title, &entries, histogram.values().sum());
+}
+
+/// Prints counts ordered by count, highest first
+pub fn print_ranking(title: &str, ranking: &BTreeMap<String, usize>) {
+    let mut entries = ranking.iter().collect::<Vec<_>>();
+    entries.sort_by(|(a, x), (b, y)| y.cmp(x).then_with(|| a.cmp(b)));
+
+    print_bars(title, &entries, ranking.values().copied().max().unwrap_or(0));
+}
+
+fn print_bars(title: &str, entries: &[(&String, &usize)], total: usize) {
+    let total = total.max(1);
+
+    println!("{}:", title);
+    for (key, count) in entries {
+        let bar = "●".repeat((*count * 50).div_ceil(total));
+        println!("  {:<30} {:>6} {}", key, count, bar);
+    }
+}
+
+fn read_dirs(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut result = Vec::new();
+
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if !entry.file_type()?.is_dir() || file_name(&path).is_none_or(|n| n.starts_with('.')) {
+            continue;
+        }
+        result.push(path);
+    }
+
+    Ok(result)
+}
+
+fn file_name(path: &Path) -> Option<String> {
+    path.file_name().and_then(|n| n.to_str()).map(|n| n.to_string())
+}
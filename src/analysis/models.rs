@@ -0,0 +1,308 @@
+use std::collections::BTreeMap;
+use std::sync::LazyLock;
+
+use anyhow::{Context, Result};
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::metadata::{self, MetadataStatus};
+use super::{print_ranking, xml_attribute, AppFiles, LocalApp};
+
+pub const MODELS_DIR: &str = "App/models";
+/// Files that make up a model, the longest first since `.schema.json` also ends with `.json`
+const MODEL_EXTENSIONS: [&str; 4] = ["schema.json", "metadata.json", "xsd", "cs"];
+
+static CLASS_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)^\s*public\s+(?:partial\s+)?class\s+(\w+)").unwrap());
+static PROPERTY_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"public\s+[\w<>\[\]?,.]+(?:\s*<[^>]*>)?\??\s+(\w+)\s*\{\s*get;").unwrap());
+static XML_ROOT_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"\[XmlRoot\s*\(\s*ElementName\s*=\s*"([^"]+)""#).unwrap());
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DataModel {
+    /// File name without the model extension, which is shared between the files of a model
+    pub name: String,
+    pub files: Vec<String>,
+    pub root_element: Option<String>,
+    pub xsd_fields: Option<usize>,
+    pub json_schema_fields: Option<usize>,
+    pub class_fields: Option<usize>,
+    pub classes: Vec<String>,
+    /// Id of the dataType in applicationmetadata.json that uses this model
+    pub data_type: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppModels {
+    pub models: Vec<DataModel>,
+    /// DataTypes with app logic whose model couldn't be found
+    pub unmatched_data_types: Vec<String>,
+    pub errors: Vec<String>,
+}
+
+pub fn load(files: &(impl AppFiles + ?Sized)) -> Result<AppModels> {
+    let mut result = AppModels::default();
+    let mut models = BTreeMap::<String, DataModel>::new();
+
+    for path in files.list_files(MODELS_DIR)? {
+        let file_name = path.rsplit('/').next().unwrap_or(&path);
+        let Some((name, extension)) = split_model_file(file_name) else {
+            continue;
+        };
+        let Some(content) = files.read_file(&path)? else {
+            continue;
+        };
+
+        let model = models.entry(name.to_string()).or_insert_with(|| DataModel {
+            name: name.to_string(),
+            ..Default::default()
+        });
+        model.files.push(path.clone());
+
+        let parsed = match extension {
+            "xsd" => parse_xsd(&content).map(|(root, fields)| {
+                model.root_element = model.root_element.take().or(root);
+                model.xsd_fields = Some(fields);
+            }),
+            "schema.json" => parse_json_schema(&content).map(|(root, fields)| {
+                model.root_element = model.root_element.take().or(root);
+                model.json_schema_fields = Some(fields);
+            }),
+            "cs" => {
+                let (root, classes, fields) = parse_class(&content);
+                model.root_element = model.root_element.take().or(root);
+                model.classes = classes;
+                model.class_fields = Some(fields);
+                Ok(())
+            }
+            _ => Ok(()),
+        };
+        if let Err(e) = parsed {
+            result.errors.push(format!("{}: {:#}", path, e));
+        }
+    }
+
+    result.models = models.into_values().collect();
+
+    if let MetadataStatus::Parsed(metadata) = metadata::load(files) {
+        for data_type in metadata.data_types.iter().filter(|d| d.app_logic.is_some()) {
+            let class_name = data_type
+                .app_logic
+                .as_ref()
+                .and_then(|l| l.class_ref.as_deref())
+                .and_then(|c| c.rsplit('.').next());
+
+            // Model files are usually named after the dataType, otherwise fall back to the class reference
+            let index = result.models.iter().position(|m| m.name == data_type.id).or_else(|| {
+                let class_name = class_name?;
+                result
+                    .models
+                    .iter()
+                    .position(|m| m.classes.iter().any(|c| c == class_name))
+            });
+            match index.map(|i| &mut result.models[i]) {
+                Some(model) => model.data_type = Some(data_type.id.clone()),
+                None => result.unmatched_data_types.push(data_type.id.clone()),
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+/// Splits a file name into the model name and the model extension, e.g. `model.v2.schema.json`
+/// into `model.v2` and `schema.json`. Returns `None` for files that aren't part of a model
+fn split_model_file(file_name: &str) -> Option<(&str, &'static str)> {
+    MODEL_EXTENSIONS.iter().find_map(|extension| {
+        let name = file_name.strip_suffix(extension)?.strip_suffix('.')?;
+        (!name.is_empty()).then_some((name, *extension))
+    })
+}
+
+/// Returns the root element and the number of element and attribute declarations below it
+fn parse_xsd(content: &str) -> Result<(Option<String>, usize)> {
+    let mut reader = Reader::from_str(content);
+    reader.trim_text(true);
+
+    let mut depth = 0;
+    let mut root = None;
+    let mut fields = 0;
+
+    loop {
+        let event = reader.read_event().context("Invalid XML")?;
+        let (e, empty) = match &event {
+            Event::Start(e) => (e, false),
+            Event::Empty(e) => (e, true),
+            Event::End(_) => {
+                depth -= 1;
+                continue;
+            }
+            Event::Eof => break,
+            _ => continue,
+        };
+
+        let name = e.local_name();
+        if matches!(name.as_ref(), b"element" | b"attribute") {
+            if let Some(element_name) = xml_attribute(e, "name")? {
+                // Top level elements are direct children of the schema element
+                if depth == 1 && root.is_none() && name.as_ref() == b"element" {
+                    root = Some(element_name);
+                } else {
+                    fields += 1;
+                }
+            }
+        }
+
+        if !empty {
+            depth += 1;
+        }
+    }
+
+    Ok((root, fields))
+}
+
+/// Returns the root definition and the number of properties across all definitions
+fn parse_json_schema(content: &str) -> Result<(Option<String>, usize)> {
+    let schema = serde_json::from_str::<Value>(content).context("Invalid JSON")?;
+
+    let root_ref = schema
+        .get("$ref")
+        .or_else(|| schema.pointer("/oneOf/0/$ref"))
+        .and_then(|r| r.as_str());
+    let root = root_ref
+        .and_then(|r| r.rsplit('/').next())
+        .or_else(|| schema.get("title").and_then(|t| t.as_str()))
+        .map(|r| r.to_string());
+
+    fn count_properties(value: &Value) -> usize {
+        match value {
+            Value::Object(map) => {
+                let own = map.get("properties").and_then(|p| p.as_object()).map_or(0, |p| p.len());
+                own + map.values().map(count_properties).sum::<usize>()
+            }
+            Value::Array(items) => items.iter().map(count_properties).sum(),
+            _ => 0,
+        }
+    }
+
+    Ok((root, count_properties(&schema)))
+}
+
+/// Returns the XML root element name, the declared classes and the number of properties
+fn parse_class(content: &str) -> (Option<String>, Vec<String>, usize) {
+    let root = XML_ROOT_REGEX.captures(content).map(|c| c[1].to_string());
+    let classes = CLASS_REGEX.captures_iter(content).map(|c| c[1].to_string()).collect();
+    let fields = PROPERTY_REGEX.find_iter(content).count();
+
+    (root, classes, fields)
+}
+
+pub fn report(apps: &[LocalApp]) -> Result<()> {
+    let mut kinds = BTreeMap::<String, usize>::new();
+    let mut with_unmatched = 0;
+    let mut with_unlinked = 0;
+    let mut failed = Vec::new();
+
+    for app in apps {
+        let models = match load(&app.dir) {
+            Ok(models) => models,
+            Err(err) => {
+                failed.push((app, format!("{:#}", err)));
+                continue;
+            }
+        };
+        for err in &models.errors {
+            failed.push((app, err.clone()));
+        }
+
+        println!("{:<60} models={}", app.key(), models.models.len());
+        for model in &models.models {
+            let files = model
+                .files
+                .iter()
+                .filter_map(|f| split_model_file(f.rsplit('/').next()?).map(|(_, ext)| ext))
+                .collect::<Vec<_>>();
+            for file in &files {
+                *kinds.entry(file.to_string()).or_default() += 1;
+            }
+
+            let count = |c: Option<usize>| c.map_or("-".to_string(), |c| c.to_string());
+            println!(
+                "  {:<40} root={:<30} fields=xsd:{}/json:{}/cs:{} dataType={} files={}",
+                model.name,
+                model.root_element.as_deref().unwrap_or("-"),
+                count(model.xsd_fields),
+                count(model.json_schema_fields),
+                count(model.class_fields),
+                model.data_type.as_deref().unwrap_or("-"),
+                files.join(",")
+            );
+        }
+
+        for data_type in &models.unmatched_data_types {
+            println!("  WARNING: dataType {} has no matching model file", data_type);
+        }
+        let unlinked = models.models.iter().filter(|m| m.data_type.is_none()).count();
+        with_unmatched += usize::from(!models.unmatched_data_types.is_empty());
+        with_unlinked += usize::from(unlinked > 0);
+    }
+
+    println!("--------------------------------------------------");
+    print_ranking("Model files", &kinds);
+    println!("Apps with dataTypes without a model: {}", with_unmatched);
+    println!("Apps with models not linked to a dataType: {}", with_unlinked);
+
+    println!("--------------------------------------------------");
+    println!("Failed to parse models ({}):", failed.len());
+    for (app, err) in &failed {
+        println!("  {}: {}", app.key(), err);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_model_files_at_the_model_extension() {
+        assert_eq!(
+            split_model_file("model.v2.schema.json"),
+            Some(("model.v2", "schema.json"))
+        );
+        assert_eq!(
+            split_model_file("model.metadata.json"),
+            Some(("model", "metadata.json"))
+        );
+        assert_eq!(split_model_file("model.v2.xsd"), Some(("model.v2", "xsd")));
+        assert_eq!(split_model_file("Model.cs"), Some(("Model", "cs")));
+        assert_eq!(split_model_file("model.json"), None);
+        assert_eq!(split_model_file(".cs"), None);
+    }
+
+    #[test]
+    fn counts_xsd_fields_without_the_root_element() {
+        let (root, fields) = parse_xsd(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+  <xs:element name="Skjema" type="Skjema" />
+  <xs:complexType name="Skjema">
+    <xs:sequence>
+      <xs:element name="navn" type="xs:string" />
+      <xs:element name="alder" type="xs:int" />
+    </xs:sequence>
+    <xs:attribute name="versjon" type="xs:string" />
+  </xs:complexType>
+</xs:schema>"#,
+        )
+        .unwrap();
+
+        assert_eq!(root.as_deref(), Some("Skjema"));
+        assert_eq!(fields, 3);
+    }
+}
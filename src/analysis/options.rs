@@ -0,0 +1,295 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::LazyLock;
+
+use anyhow::Result;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::code::PROGRAM_PATH;
+use super::{code, layouts, print_ranking, AppFiles, LocalApp};
+
+pub const OPTIONS_DIR: &str = "App/options";
+
+/// The `Id` property or field, set to either a string literal or a constant
+static ID_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"\bId\s*(?:\{[^}]*\}\s*=|=>|=)\s*(?:"([^"]+)"|([A-Za-z_][\w.]*)\s*;)"#).unwrap());
+static CONST_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"\bconst\s+string\s+(\w+)\s*=\s*"([^"]+)""#).unwrap());
+static CLASS_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\bclass\s+(\w+)").unwrap());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OptionsKind {
+    /// JSON file in `App/options`
+    Static,
+    /// `IAppOptionsProvider` implementation
+    Dynamic,
+    /// `IInstanceAppOptionsProvider` implementation, only available in the context of an instance
+    Secured,
+}
+
+impl fmt::Display for OptionsKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            OptionsKind::Static => "static",
+            OptionsKind::Dynamic => "dynamic",
+            OptionsKind::Secured => "secured",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OptionsDefinition {
+    pub id: String,
+    pub kind: OptionsKind,
+    pub path: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppOptions {
+    pub definitions: Vec<OptionsDefinition>,
+    /// Option id -> number of components referencing it through `optionsId`
+    pub references: BTreeMap<String, usize>,
+    /// Whether the app registers the shared code lists from the Altinn.Codelists package,
+    /// which define option ids that can't be found in the app itself
+    pub uses_codelists: bool,
+    pub errors: Vec<String>,
+}
+
+impl AppOptions {
+    /// Referenced ids that aren't defined anywhere. Apps using Altinn.Codelists have none,
+    /// since those ids can't be told apart from the ones the package defines
+    pub fn undefined(&self) -> Vec<&str> {
+        if self.uses_codelists {
+            return Vec::new();
+        }
+        self.not_defined_in_app()
+    }
+
+    /// Referenced ids that aren't defined in the app, but are likely defined by Altinn.Codelists
+    pub fn codelist_references(&self) -> Vec<&str> {
+        if !self.uses_codelists {
+            return Vec::new();
+        }
+        self.not_defined_in_app()
+    }
+
+    fn not_defined_in_app(&self) -> Vec<&str> {
+        self.references
+            .keys()
+            .filter(|id| !self.definitions.iter().any(|d| &d.id == *id))
+            .map(|id| id.as_str())
+            .collect()
+    }
+}
+
+pub fn load(files: &(impl AppFiles + ?Sized)) -> Result<AppOptions> {
+    let mut result = AppOptions::default();
+
+    for path in files.list_files(OPTIONS_DIR)? {
+        let Some(id) = path.rsplit('/').next().and_then(|n| n.strip_suffix(".json")) else {
+            continue;
+        };
+        result.definitions.push(OptionsDefinition {
+            id: id.to_string(),
+            kind: OptionsKind::Static,
+            path: path.clone(),
+        });
+    }
+
+    let code = code::load(files)?;
+    for implementation in &code.implementations {
+        let kind = match implementation.extension_point.as_str() {
+            "IAppOptionsProvider" => OptionsKind::Dynamic,
+            "IInstanceAppOptionsProvider" => OptionsKind::Secured,
+            _ => continue,
+        };
+        let Some(content) = files.read_file(&implementation.path)? else {
+            continue;
+        };
+
+        match provider_id(&content, &implementation.class) {
+            Some(id) => result.definitions.push(OptionsDefinition {
+                id,
+                kind,
+                path: implementation.path.clone(),
+            }),
+            None => result.errors.push(format!(
+                "{}: couldn't find the options id of {}",
+                implementation.path, implementation.class
+            )),
+        }
+    }
+
+    if let Some(program) = files.read_file(PROGRAM_PATH)? {
+        result.uses_codelists = program.contains("AddAltinnCodelists");
+    }
+
+    let layouts = layouts::load(files)?;
+    result.errors.extend(layouts.errors.iter().cloned());
+    for (_, _, component) in layouts.components() {
+        if let Some(id) = component.property("optionsId").and_then(|id| id.as_str()) {
+            *result.references.entry(id.to_string()).or_default() += 1;
+        }
+    }
+
+    Ok(result)
+}
+
+/// Finds the value of the `Id` property within the body of the given class, resolving string constants
+/// declared anywhere in the file
+fn provider_id(content: &str, class: &str) -> Option<String> {
+    let mut classes = CLASS_REGEX.captures_iter(content).skip_while(|c| &c[1] != class);
+    let start = classes.next()?.get(0)?.start();
+    let end = classes.next().map_or(content.len(), |c| c.get(0).unwrap().start());
+
+    let captures = ID_REGEX.captures(&content[start..end])?;
+    if let Some(literal) = captures.get(1) {
+        return Some(literal.as_str().to_string());
+    }
+
+    let constant = captures[2].rsplit('.').next()?;
+    CONST_REGEX
+        .captures_iter(content)
+        .find(|c| &c[1] == constant)
+        .map(|c| c[2].to_string())
+}
+
+pub fn report(apps: &[LocalApp]) -> Result<()> {
+    let mut kinds = BTreeMap::<String, usize>::new();
+    let mut with_undefined = 0;
+    let mut failed = Vec::new();
+
+    for app in apps {
+        let options = match load(&app.dir) {
+            Ok(options) => options,
+            Err(err) => {
+                failed.push((app, format!("{:#}", err)));
+                continue;
+            }
+        };
+        for err in &options.errors {
+            failed.push((app, err.clone()));
+        }
+
+        let count = |kind| options.definitions.iter().filter(|d| d.kind == kind).count();
+        let undefined = options.undefined();
+        println!(
+            "{:<60} static={:<3} dynamic={:<3} secured={:<3} references={:<3} undefined={:<3}{}",
+            app.key(),
+            count(OptionsKind::Static),
+            count(OptionsKind::Dynamic),
+            count(OptionsKind::Secured),
+            options.references.len(),
+            undefined.len(),
+            if options.uses_codelists {
+                " (uses Altinn.Codelists)"
+            } else {
+                ""
+            }
+        );
+        for definition in &options.definitions {
+            println!(
+                "  {:<40} {:<8} references={:<3} {}",
+                definition.id,
+                definition.kind,
+                options.references.get(&definition.id).copied().unwrap_or(0),
+                definition.path
+            );
+        }
+        if !undefined.is_empty() {
+            println!("  referenced from layouts but not defined: {}", undefined.join(", "));
+        }
+        let codelists = options.codelist_references();
+        if !codelists.is_empty() {
+            println!(
+                "  referenced from layouts and assumed to be from Altinn.Codelists: {}",
+                codelists.join(", ")
+            );
+        }
+
+        for definition in &options.definitions {
+            *kinds.entry(definition.kind.to_string()).or_default() += 1;
+        }
+        with_undefined += usize::from(!undefined.is_empty());
+    }
+
+    println!("--------------------------------------------------");
+    print_ranking("Option lists by kind", &kinds);
+    println!("Apps referencing undefined option ids: {}", with_undefined);
+
+    println!("--------------------------------------------------");
+    println!("Failed to analyze options ({}):", failed.len());
+    for (app, err) in &failed {
+        println!("  {}: {}", app.key(), err);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_ids_of_property_initializers() {
+        let content = r#"
+            public class CountryOptions : IAppOptionsProvider
+            {
+                public string Id { get; set; } = "countries";
+            }
+        "#;
+
+        assert_eq!(provider_id(content, "CountryOptions").as_deref(), Some("countries"));
+    }
+
+    #[test]
+    fn finds_ids_of_expression_bodied_properties_in_the_given_class() {
+        let content = r#"
+            public class Helper
+            {
+                public string Id => "helper";
+            }
+
+            public class MunicipalityOptions : IInstanceAppOptionsProvider
+            {
+                public string Id => "municipalities";
+            }
+        "#;
+
+        assert_eq!(
+            provider_id(content, "MunicipalityOptions").as_deref(),
+            Some("municipalities")
+        );
+        assert_eq!(provider_id(content, "Helper").as_deref(), Some("helper"));
+        assert_eq!(provider_id(content, "Missing"), None);
+    }
+
+    #[test]
+    fn finds_ids_of_const_fields_and_constants() {
+        let field = r#"
+            public class CountryOptions : IAppOptionsProvider
+            {
+                public const string Id = "countries";
+            }
+        "#;
+        let constant = r#"
+            public static class OptionIds
+            {
+                public const string Municipalities = "municipalities";
+            }
+
+            public class MunicipalityOptions : IAppOptionsProvider
+            {
+                public string Id { get; } = OptionIds.Municipalities;
+            }
+        "#;
+
+        assert_eq!(provider_id(field, "CountryOptions").as_deref(), Some("countries"));
+        assert_eq!(
+            provider_id(constant, "MunicipalityOptions").as_deref(),
+            Some("municipalities")
+        );
+    }
+}
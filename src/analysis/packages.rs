@@ -0,0 +1,573 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::LazyLock;
+
+use anyhow::{Context, Result};
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::{print_histogram, AppFiles, LocalApp};
+
+pub const MAIN_PROJECT: &str = "App/App.csproj";
+const PACKAGE_PREFIX: &str = "Altinn.App.";
+/// Imported by MSBuild from the closest dir at or above the project
+pub const BUILD_PROPS: &str = "Directory.Build.props";
+/// Central package management, where the versions of package references are declared
+pub const PACKAGES_PROPS: &str = "Directory.Packages.props";
+
+static PROPERTY_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\$\(([A-Za-z_][\w.-]*)\)").unwrap());
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Project {
+    pub path: String,
+    pub target_frameworks: Vec<String>,
+    pub packages: Vec<PackageReference>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PackageReference {
+    pub name: String,
+    pub version: Option<String>,
+}
+
+impl Project {
+    /// Version of the given package, if referenced by this project
+    pub fn version_of(&self, package: &str) -> Option<&str> {
+        self.packages
+            .iter()
+            .find(|p| p.name.eq_ignore_ascii_case(package))
+            .and_then(|p| p.version.as_deref())
+    }
+}
+
+/// Parses all .csproj files in the app, with `App/App.csproj` first if it exists.
+/// Versions are resolved from `Directory.Build.props` and `Directory.Packages.props` like MSBuild does
+pub fn load(files: &(impl AppFiles + ?Sized)) -> Result<Vec<Project>> {
+    let mut paths = files.find_files(&|path| path.ends_with(".csproj"))?;
+    paths.sort_by_key(|path| path != MAIN_PROJECT);
+
+    let mut imports = HashMap::<String, Option<MsBuildFile>>::new();
+    let mut result = Vec::with_capacity(paths.len());
+    for path in paths {
+        let Some(content) = files.read_file(&path)? else {
+            continue;
+        };
+        let project = parse_file(&content).with_context(|| format!("Failed to parse {}", path))?;
+
+        let mut import_paths = Vec::new();
+        for name in [BUILD_PROPS, PACKAGES_PROPS] {
+            import_paths.extend(closest_import(files, &path, name, &mut imports)?);
+        }
+        let mut chain = import_paths
+            .iter()
+            .filter_map(|p| imports[p].as_ref())
+            .collect::<Vec<_>>();
+        chain.push(&project);
+
+        result.push(resolve(&path, &chain));
+    }
+
+    Ok(result)
+}
+
+/// Parses a single project without any imports
+pub fn parse(path: &str, content: &str) -> Result<Project> {
+    let file = parse_file(content)?;
+    Ok(resolve(path, &[&file]))
+}
+
+/// Path of the import file closest to the project, looking in the project dir and then each parent dir.
+/// Parsed files are cached, including the ones that don't exist
+fn closest_import(
+    files: &(impl AppFiles + ?Sized),
+    project: &str,
+    name: &str,
+    cache: &mut HashMap<String, Option<MsBuildFile>>,
+) -> Result<Option<String>> {
+    let mut dir = project.rsplit_once('/').map(|(dir, _)| dir);
+    loop {
+        let path = match dir {
+            Some(dir) => format!("{}/{}", dir, name),
+            None => name.to_string(),
+        };
+        if !cache.contains_key(&path) {
+            let file = match files.read_file(&path)? {
+                Some(content) => Some(parse_file(&content).with_context(|| format!("Failed to parse {}", path))?),
+                None => None,
+            };
+            cache.insert(path.clone(), file);
+        }
+        if cache[&path].is_some() {
+            return Ok(Some(path));
+        }
+        match dir {
+            Some(d) => dir = d.rsplit_once('/').map(|(parent, _)| parent),
+            None => return Ok(None),
+        }
+    }
+}
+
+/// What is declared in a single MSBuild file, before properties and versions are resolved.
+/// Conditions aren't evaluated, so the last declaration wins
+#[derive(Default, Debug, Clone)]
+struct MsBuildFile {
+    /// In declaration order with lowercase names, since MSBuild property names are case insensitive
+    properties: Vec<(String, String)>,
+    /// `PackageVersion` items from central package management, keyed by lowercase package name
+    package_versions: HashMap<String, String>,
+    packages: Vec<PackageReference>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ItemKind {
+    Include,
+    /// Changes the items included before it instead of adding a new one
+    Update,
+    PackageVersion,
+}
+
+/// A package item being parsed, whose version can be given as an attribute or a child element
+struct Item {
+    kind: ItemKind,
+    package: PackageReference,
+    version_override: Option<String>,
+}
+
+fn parse_file(content: &str) -> Result<MsBuildFile> {
+    let mut reader = Reader::from_str(content);
+    reader.trim_text(true);
+
+    let mut file = MsBuildFile::default();
+    let mut item = None::<Item>;
+
+    // Tracks the element we're in, since both properties and package versions are child elements
+    let mut current = Vec::<String>::new();
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) => {
+                let name = String::from_utf8_lossy(e.local_name().as_ref()).to_string();
+                if let Some(kind) = item_kind(&name) {
+                    item = Some(package_item(kind, &e)?);
+                }
+                current.push(name);
+            }
+            Event::Empty(e) => {
+                let name = String::from_utf8_lossy(e.local_name().as_ref()).to_string();
+                if let Some(kind) = item_kind(&name) {
+                    add_item(&mut file, package_item(kind, &e)?);
+                }
+            }
+            Event::End(_) => {
+                let name = current.pop();
+                if name.as_deref().and_then(item_kind).is_some() {
+                    if let Some(item) = item.take() {
+                        add_item(&mut file, item);
+                    }
+                }
+            }
+            Event::Text(e) => {
+                let text = e.unescape()?.trim().to_string();
+                let (Some(name), Some(parent)) = (current.last(), current.iter().rev().nth(1)) else {
+                    continue;
+                };
+                match (parent.as_str(), &mut item) {
+                    ("PropertyGroup", _) => {
+                        file.properties.push((name.to_ascii_lowercase(), text));
+                    }
+                    (parent, Some(item)) if item_kind(parent).is_some() => match name.as_str() {
+                        "Version" => item.package.version = Some(text),
+                        "VersionOverride" => item.version_override = Some(text),
+                        _ => {}
+                    },
+                    _ => {}
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(file)
+}
+
+fn item_kind(element: &str) -> Option<ItemKind> {
+    match element {
+        "PackageReference" => Some(ItemKind::Include),
+        "PackageVersion" => Some(ItemKind::PackageVersion),
+        _ => None,
+    }
+}
+
+fn package_item(mut kind: ItemKind, e: &quick_xml::events::BytesStart) -> Result<Item> {
+    let mut item = Item {
+        kind,
+        package: PackageReference::default(),
+        version_override: None,
+    };
+
+    for attr in e.attributes() {
+        let attr = attr?;
+        let value = attr.unescape_value()?.trim().to_string();
+        match attr.key.local_name().as_ref() {
+            b"Include" => item.package.name = value,
+            b"Update" if kind == ItemKind::Include => {
+                kind = ItemKind::Update;
+                item.package.name = value;
+            }
+            b"Version" => item.package.version = Some(value),
+            b"VersionOverride" => item.version_override = Some(value),
+            _ => {}
+        }
+    }
+    item.kind = kind;
+
+    Ok(item)
+}
+
+fn add_item(file: &mut MsBuildFile, item: Item) {
+    let Item {
+        kind,
+        mut package,
+        version_override,
+    } = item;
+    if package.name.is_empty() {
+        return;
+    }
+    if version_override.is_some() {
+        package.version = version_override;
+    }
+
+    match kind {
+        ItemKind::Include => file.packages.push(package),
+        ItemKind::Update => {
+            let Some(version) = package.version else {
+                return;
+            };
+            for existing in file.packages.iter_mut() {
+                if existing.name.eq_ignore_ascii_case(&package.name) {
+                    existing.version = Some(version.clone());
+                }
+            }
+        }
+        ItemKind::PackageVersion => {
+            if let Some(version) = package.version {
+                file.package_versions.insert(package.name.to_ascii_lowercase(), version);
+            }
+        }
+    }
+}
+
+/// Combines the project with its imports, which are given in import order with the project last.
+/// Like MSBuild, properties are evaluated in order, so they only see the properties declared before them,
+/// while items are evaluated afterwards and see the final values
+fn resolve(path: &str, chain: &[&MsBuildFile]) -> Project {
+    let mut properties = HashMap::<&str, String>::new();
+    let mut package_versions = HashMap::new();
+    for file in chain {
+        for (name, value) in &file.properties {
+            let value = expand(value, &properties);
+            properties.insert(name.as_str(), value);
+        }
+        package_versions.extend(file.package_versions.iter().map(|(k, v)| (k.as_str(), v.as_str())));
+    }
+
+    let target_frameworks = properties
+        .get("targetframeworks")
+        .or_else(|| properties.get("targetframework"))
+        .cloned()
+        .unwrap_or_default();
+    let target_frameworks = target_frameworks
+        .split(';')
+        .map(|f| f.trim())
+        .filter(|f| !f.is_empty())
+        .map(|f| f.to_string())
+        .collect();
+
+    let project = chain.last().map(|f| f.packages.as_slice()).unwrap_or_default();
+    let packages = project
+        .iter()
+        .map(|package| {
+            let version = package
+                .version
+                .as_deref()
+                .or_else(|| {
+                    package_versions
+                        .get(package.name.to_ascii_lowercase().as_str())
+                        .copied()
+                })
+                .map(|v| expand(v, &properties));
+            PackageReference {
+                name: package.name.clone(),
+                version,
+            }
+        })
+        .collect();
+
+    Project {
+        path: path.to_string(),
+        target_frameworks,
+        packages,
+    }
+}
+
+/// Replaces `$(Name)` with the value of the property, leaving unknown properties as they are.
+/// The values are already expanded, so a single pass is enough
+fn expand(value: &str, properties: &HashMap<&str, String>) -> String {
+    PROPERTY_REGEX
+        .replace_all(value, |caps: &regex::Captures| {
+            let name = caps[1].to_ascii_lowercase();
+            properties.get(name.as_str()).map_or(caps[0].to_string(), |v| v.clone())
+        })
+        .into_owned()
+}
+
+pub fn report(apps: &[LocalApp]) -> Result<()> {
+    let mut package_versions = BTreeMap::<String, BTreeMap<String, usize>>::new();
+    let mut frameworks = BTreeMap::<String, usize>::new();
+    let mut missing = Vec::new();
+    let mut failed = Vec::new();
+
+    for app in apps {
+        let projects = match load(&app.dir) {
+            Ok(projects) if projects.is_empty() => {
+                missing.push(app);
+                continue;
+            }
+            Ok(projects) => projects,
+            Err(err) => {
+                failed.push((app, err));
+                continue;
+            }
+        };
+
+        // Counted once per app, so that apps with several projects don't skew the numbers
+        let mut app_packages = BTreeMap::<String, String>::new();
+        let mut app_frameworks = Vec::<String>::new();
+        for project in &projects {
+            for package in project.packages.iter().filter(|p| p.name.starts_with(PACKAGE_PREFIX)) {
+                let version = package.version.clone().unwrap_or_else(|| "<none>".to_string());
+                app_packages.entry(package.name.clone()).or_insert(version);
+            }
+            for framework in &project.target_frameworks {
+                if !app_frameworks.contains(framework) {
+                    app_frameworks.push(framework.clone());
+                }
+            }
+        }
+
+        for (name, version) in &app_packages {
+            *package_versions
+                .entry(name.clone())
+                .or_default()
+                .entry(version.clone())
+                .or_default() += 1;
+        }
+        for framework in &app_frameworks {
+            *frameworks.entry(framework.clone()).or_default() += 1;
+        }
+
+        println!(
+            "{:<60} projects={:<2} framework={:<20} {}",
+            app.key(),
+            projects.len(),
+            app_frameworks.join(";"),
+            app_packages
+                .iter()
+                .map(|(name, version)| format!("{}={}", name, version))
+                .collect::<Vec<_>>()
+                .join(" ")
+        );
+    }
+
+    println!("--------------------------------------------------");
+    for (package, versions) in &package_versions {
+        print_histogram(package, versions);
+    }
+    print_histogram("TargetFramework", &frameworks);
+
+    println!("--------------------------------------------------");
+    println!("No .csproj files ({}):", missing.len());
+    for app in &missing {
+        println!("  {}", app.key());
+    }
+
+    println!("Failed to parse .csproj files ({}):", failed.len());
+    for (app, err) in &failed {
+        println!("  {}: {:#}", app.key(), err);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::analysis::MemoryFiles;
+
+    fn versions(project: &Project) -> Vec<(&str, Option<&str>)> {
+        project
+            .packages
+            .iter()
+            .map(|p| (p.name.as_str(), p.version.as_deref()))
+            .collect()
+    }
+
+    #[test]
+    fn parses_attribute_and_element_versions() {
+        let project = parse(
+            MAIN_PROJECT,
+            r#"<Project Sdk="Microsoft.NET.Sdk.Web">
+                <PropertyGroup>
+                    <TargetFramework>net8.0</TargetFramework>
+                </PropertyGroup>
+                <ItemGroup>
+                    <PackageReference Include="Altinn.App.Api" Version="8.0.0" />
+                    <PackageReference Include="Altinn.App.Core">
+                        <Version>8.0.1</Version>
+                    </PackageReference>
+                    <PackageReference Include="Newtonsoft.Json" />
+                </ItemGroup>
+            </Project>"#,
+        )
+        .unwrap();
+
+        assert_eq!(project.target_frameworks, vec!["net8.0"]);
+        assert_eq!(
+            versions(&project),
+            vec![
+                ("Altinn.App.Api", Some("8.0.0")),
+                ("Altinn.App.Core", Some("8.0.1")),
+                ("Newtonsoft.Json", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn update_items_change_versions_instead_of_adding_references() {
+        let project = parse(
+            MAIN_PROJECT,
+            r#"<Project>
+                <ItemGroup>
+                    <PackageReference Include="Altinn.App.Core" Version="7.0.0" />
+                    <PackageReference Update="Altinn.App.Core" Version="7.1.0" />
+                    <PackageReference Update="Altinn.App.Api">
+                        <Version>7.1.0</Version>
+                    </PackageReference>
+                </ItemGroup>
+            </Project>"#,
+        )
+        .unwrap();
+
+        assert_eq!(versions(&project), vec![("Altinn.App.Core", Some("7.1.0"))]);
+    }
+
+    #[test]
+    fn expands_properties() {
+        let project = parse(
+            MAIN_PROJECT,
+            r#"<Project>
+                <PropertyGroup>
+                    <Framework>net8.0</Framework>
+                    <TargetFrameworks>$(Framework);net6.0</TargetFrameworks>
+                    <AppLibMajor>8</AppLibMajor>
+                    <AppLibVersion>$(AppLibMajor).2</AppLibVersion>
+                    <AppLibVersion>$(AppLibVersion).0</AppLibVersion>
+                </PropertyGroup>
+                <ItemGroup>
+                    <PackageReference Include="Altinn.App.Api" Version="$(AppLibVersion)" />
+                    <PackageReference Include="Altinn.App.Core" Version="$(Unknown)" />
+                </ItemGroup>
+            </Project>"#,
+        )
+        .unwrap();
+
+        assert_eq!(project.target_frameworks, vec!["net8.0", "net6.0"]);
+        assert_eq!(
+            versions(&project),
+            vec![
+                ("Altinn.App.Api", Some("8.2.0")),
+                ("Altinn.App.Core", Some("$(Unknown)"))
+            ]
+        );
+    }
+
+    #[test]
+    fn properties_only_see_the_properties_declared_before_them() {
+        let project = parse(
+            MAIN_PROJECT,
+            r#"<Project>
+                <PropertyGroup>
+                    <TargetFramework>$(Framework)</TargetFramework>
+                    <Framework>net8.0</Framework>
+                </PropertyGroup>
+                <ItemGroup>
+                    <PackageReference Include="Altinn.App.Api" Version="$(AppLibVersion)" />
+                </ItemGroup>
+                <PropertyGroup>
+                    <AppLibVersion>8.0.0</AppLibVersion>
+                </PropertyGroup>
+            </Project>"#,
+        )
+        .unwrap();
+
+        assert_eq!(project.target_frameworks, vec!["$(Framework)"]);
+        // Items are evaluated after all properties
+        assert_eq!(versions(&project), vec![("Altinn.App.Api", Some("8.0.0"))]);
+    }
+
+    #[test]
+    fn resolves_central_package_management() {
+        let files = MemoryFiles(HashMap::from([
+            (
+                MAIN_PROJECT,
+                r#"<Project>
+                    <ItemGroup>
+                        <PackageReference Include="Altinn.App.Api" />
+                        <PackageReference Include="Altinn.App.Core" VersionOverride="8.1.0" />
+                    </ItemGroup>
+                </Project>"#,
+            ),
+            (
+                "Directory.Packages.props",
+                r#"<Project>
+                    <PropertyGroup>
+                        <AppLibVersion>8.0.0</AppLibVersion>
+                    </PropertyGroup>
+                    <ItemGroup>
+                        <PackageVersion Include="Altinn.App.Api" Version="$(AppLibVersion)" />
+                        <PackageVersion Include="Altinn.App.Core" Version="$(AppLibVersion)" />
+                    </ItemGroup>
+                </Project>"#,
+            ),
+            (
+                "App/Directory.Build.props",
+                r#"<Project>
+                    <PropertyGroup>
+                        <TargetFramework>net8.0</TargetFramework>
+                    </PropertyGroup>
+                </Project>"#,
+            ),
+            (
+                "Directory.Build.props",
+                r#"<Project>
+                    <PropertyGroup>
+                        <TargetFramework>net6.0</TargetFramework>
+                    </PropertyGroup>
+                </Project>"#,
+            ),
+        ]));
+
+        let projects = load(&files).unwrap();
+
+        assert_eq!(projects.len(), 1);
+        assert_eq!(projects[0].target_frameworks, vec!["net8.0"]);
+        assert_eq!(
+            versions(&projects[0]),
+            vec![("Altinn.App.Api", Some("8.0.0")), ("Altinn.App.Core", Some("8.1.0"))]
+        );
+    }
+}
@@ -0,0 +1,449 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::Result;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use serde::{Deserialize, Serialize};
+
+use super::{print_histogram, print_ranking, xml_attribute as attribute, AppFiles, LocalApp};
+
+pub const PATH: &str = "App/config/authorization/policy.xml";
+
+const ACTION_ID: &str = "urn:oasis:names:tc:xacml:1.0:action:action-id";
+const AUTHENTICATION_LEVEL_CATEGORY: &str = "urn:altinn:minimum-authenticationlevel";
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Policy {
+    pub policy_id: Option<String>,
+    pub rules: Vec<Rule>,
+    pub obligations: Vec<Obligation>,
+}
+
+/// A rule target is a list of alternatives (`AnyOf`), where each alternative
+/// is a list of attributes that all have to match (`AllOf`)
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rule {
+    pub id: String,
+    pub effect: String,
+    pub description: Option<String>,
+    pub subjects: Vec<Vec<Attribute>>,
+    pub resources: Vec<Vec<Attribute>>,
+    pub actions: Vec<Vec<Attribute>>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Attribute {
+    pub id: String,
+    pub value: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Obligation {
+    pub id: String,
+    pub fulfill_on: Option<String>,
+    pub assignments: Vec<Assignment>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Assignment {
+    pub category: String,
+    pub value: String,
+}
+
+impl Attribute {
+    /// Compact form for reports, e.g. `rolecode=DAGL` or `read` for actions
+    pub fn short(&self) -> String {
+        if self.id == ACTION_ID {
+            return self.value.clone();
+        }
+        let id = self.id.rsplit(':').next().unwrap_or(&self.id);
+        format!("{}={}", id, self.value)
+    }
+}
+
+impl Rule {
+    pub fn action_names(&self) -> BTreeSet<String> {
+        self.actions.iter().flatten().map(|a| a.short()).collect()
+    }
+}
+
+impl Policy {
+    /// Minimum authentication level required for end users, if specified
+    pub fn authentication_level(&self) -> Option<&str> {
+        self.obligations
+            .iter()
+            .flat_map(|o| &o.assignments)
+            .find(|a| a.category == AUTHENTICATION_LEVEL_CATEGORY)
+            .map(|a| a.value.as_str())
+    }
+
+    /// Subject -> actions permitted, where a subject is one `AllOf` group of a rule
+    pub fn permissions(&self) -> BTreeMap<String, BTreeSet<String>> {
+        let mut result = BTreeMap::<String, BTreeSet<String>>::new();
+
+        for rule in self.rules.iter().filter(|r| r.effect.eq_ignore_ascii_case("Permit")) {
+            let actions = match rule.actions.is_empty() {
+                true => BTreeSet::from(["<any>".to_string()]),
+                false => rule.action_names(),
+            };
+            let subjects = match rule.subjects.is_empty() {
+                true => vec!["<any>".to_string()],
+                false => rule
+                    .subjects
+                    .iter()
+                    .map(|all_of| all_of.iter().map(|a| a.short()).collect::<Vec<_>>().join("+"))
+                    .collect(),
+            };
+            for subject in subjects {
+                result.entry(subject).or_default().extend(actions.iter().cloned());
+            }
+        }
+
+        result
+    }
+
+    /// Patterns that are likely to be mistakes or give broader access than intended
+    pub fn warnings(&self) -> Vec<Warning> {
+        let mut result = Vec::new();
+
+        match self.authentication_level() {
+            None => result.push(Warning::new(None, "missing minimum authentication level obligation")),
+            Some("0") => result.push(Warning::new(None, "minimum authentication level is 0")),
+            Some(_) => {}
+        }
+
+        for rule in self.rules.iter().filter(|r| r.effect.eq_ignore_ascii_case("Permit")) {
+            let id = Some(rule.id.as_str());
+            if rule.subjects.is_empty() {
+                result.push(Warning::new(id, "permits any subject"));
+            }
+            if rule.actions.is_empty() {
+                result.push(Warning::new(id, "permits any action"));
+            }
+            let scoped = rule
+                .resources
+                .iter()
+                .all(|all_of| all_of.iter().any(|a| a.id == "urn:altinn:app"));
+            if rule.resources.is_empty() || !scoped {
+                result.push(Warning::new(id, "not scoped to the app"));
+            }
+        }
+
+        result
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Warning {
+    pub rule: Option<String>,
+    pub message: String,
+}
+
+impl Warning {
+    fn new(rule: Option<&str>, message: &str) -> Self {
+        Warning {
+            rule: rule.map(|r| r.to_string()),
+            message: message.to_string(),
+        }
+    }
+}
+
+pub fn load(files: &(impl AppFiles + ?Sized)) -> Result<Option<Policy>> {
+    let Some(content) = files.read_file(PATH)? else {
+        return Ok(None);
+    };
+
+    parse(&content).map(Some)
+}
+
+pub fn parse(content: &str) -> Result<Policy> {
+    let mut reader = Reader::from_str(content);
+    reader.trim_text(true);
+
+    let mut policy = Policy::default();
+    let mut stack = Vec::<String>::new();
+
+    let mut all_of = Vec::<(String, Attribute)>::new();
+    let mut match_value = None::<String>;
+    let mut assignment_category = None::<String>;
+
+    loop {
+        let event = reader.read_event()?;
+        let (e, empty) = match &event {
+            Event::Start(e) => (e, false),
+            Event::Empty(e) => (e, true),
+            Event::End(e) => {
+                stack.pop();
+                if e.local_name().as_ref() == b"AllOf" && stack.iter().any(|n| n == "Rule") {
+                    add_all_of(&mut policy, std::mem::take(&mut all_of));
+                }
+                continue;
+            }
+            Event::Text(e) => {
+                let text = e.unescape()?.trim().to_string();
+                match stack.last().map(|n| n.as_str()) {
+                    Some("AttributeValue") if stack.iter().any(|n| n == "Match") => match_value = Some(text),
+                    Some("AttributeValue") if assignment_category.is_some() => {
+                        if let Some(obligation) = policy.obligations.last_mut() {
+                            obligation.assignments.push(Assignment {
+                                category: assignment_category.clone().unwrap_or_default(),
+                                value: text,
+                            });
+                        }
+                    }
+                    Some("Description") if stack.iter().rev().nth(1).map(|n| n.as_str()) == Some("Rule") => {
+                        if let Some(rule) = policy.rules.last_mut() {
+                            rule.description = Some(text);
+                        }
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+            Event::Eof => break,
+            _ => continue,
+        };
+
+        let name = String::from_utf8_lossy(e.local_name().as_ref()).to_string();
+        match name.as_str() {
+            "Policy" => policy.policy_id = attribute(e, "PolicyId")?,
+            "Rule" => policy.rules.push(Rule {
+                id: attribute(e, "RuleId")?.unwrap_or_default(),
+                effect: attribute(e, "Effect")?.unwrap_or_default(),
+                ..Default::default()
+            }),
+            "AttributeDesignator" if stack.iter().any(|n| n == "Rule") => {
+                let category = attribute(e, "Category")?.unwrap_or_default();
+                let id = attribute(e, "AttributeId")?.unwrap_or_default();
+                let value = match_value.take().unwrap_or_default();
+                all_of.push((category, Attribute { id, value }));
+            }
+            "ObligationExpression" => policy.obligations.push(Obligation {
+                id: attribute(e, "ObligationId")?.unwrap_or_default(),
+                fulfill_on: attribute(e, "FulfillOn")?,
+                assignments: Vec::new(),
+            }),
+            "AttributeAssignmentExpression" => assignment_category = attribute(e, "Category")?,
+            _ => {}
+        }
+
+        if !empty {
+            stack.push(name);
+        }
+    }
+
+    Ok(policy)
+}
+
+fn add_all_of(policy: &mut Policy, all_of: Vec<(String, Attribute)>) {
+    let Some(rule) = policy.rules.last_mut() else {
+        return;
+    };
+    let Some((category, _)) = all_of.first() else {
+        return;
+    };
+
+    let target = if category.contains("subject") {
+        &mut rule.subjects
+    } else if category.contains("action") {
+        &mut rule.actions
+    } else {
+        &mut rule.resources
+    };
+    target.push(all_of.into_iter().map(|(_, attribute)| attribute).collect());
+}
+
+pub fn report(apps: &[LocalApp]) -> Result<()> {
+    let mut warnings = BTreeMap::<String, usize>::new();
+    let mut subjects = BTreeMap::<String, usize>::new();
+    let mut authentication_levels = BTreeMap::<String, usize>::new();
+    let mut missing = Vec::new();
+    let mut failed = Vec::new();
+
+    for app in apps {
+        let policy = match load(&app.dir) {
+            Ok(Some(policy)) => policy,
+            Ok(None) => {
+                missing.push(app);
+                continue;
+            }
+            Err(err) => {
+                failed.push((app, err));
+                continue;
+            }
+        };
+
+        let authentication_level = policy.authentication_level().unwrap_or("<none>");
+        println!(
+            "{:<60} rules={:<3} authenticationLevel={}",
+            app.key(),
+            policy.rules.len(),
+            authentication_level
+        );
+        *authentication_levels
+            .entry(authentication_level.to_string())
+            .or_default() += 1;
+
+        for (subject, actions) in policy.permissions() {
+            println!(
+                "  {:<58} {}",
+                subject,
+                actions.into_iter().collect::<Vec<_>>().join(",")
+            );
+            *subjects.entry(subject).or_default() += 1;
+        }
+
+        for warning in policy.warnings() {
+            match &warning.rule {
+                Some(rule) => println!("  WARNING: {}: {}", rule, warning.message),
+                None => println!("  WARNING: {}", warning.message),
+            }
+            *warnings.entry(warning.message).or_default() += 1;
+        }
+    }
+
+    println!("--------------------------------------------------");
+    print_histogram("Minimum authentication level", &authentication_levels);
+    print_ranking("Subjects (number of apps)", &subjects);
+    print_ranking("Warnings", &warnings);
+
+    println!("--------------------------------------------------");
+    println!("Missing {} ({}):", PATH, missing.len());
+    for app in &missing {
+        println!("  {}", app.key());
+    }
+
+    println!("Failed to parse {} ({}):", PATH, failed.len());
+    for (app, err) in &failed {
+        println!("  {}: {:#}", app.key(), err);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POLICY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<xacml:Policy xmlns:xacml="urn:oasis:names:tc:xacml:3.0:core:schema:wd-17" PolicyId="urn:altinn:example:policyid:1" Version="1.0" RuleCombiningAlgId="urn:oasis:names:tc:xacml:3.0:rule-combining-algorithm:deny-overrides">
+  <xacml:Target/>
+  <xacml:Rule RuleId="urn:altinn:example:ruleid:1" Effect="Permit">
+    <xacml:Description>DAGL and REGNA can read and write</xacml:Description>
+    <xacml:Target>
+      <xacml:AnyOf>
+        <xacml:AllOf>
+          <xacml:Match MatchId="urn:oasis:names:tc:xacml:3.0:function:string-equal-ignore-case">
+            <xacml:AttributeValue DataType="http://www.w3.org/2001/XMLSchema#string">DAGL</xacml:AttributeValue>
+            <xacml:AttributeDesignator AttributeId="urn:altinn:rolecode" Category="urn:oasis:names:tc:xacml:1.0:subject-category:access-subject" DataType="http://www.w3.org/2001/XMLSchema#string" MustBePresent="false"/>
+          </xacml:Match>
+        </xacml:AllOf>
+        <xacml:AllOf>
+          <xacml:Match MatchId="urn:oasis:names:tc:xacml:3.0:function:string-equal-ignore-case">
+            <xacml:AttributeValue DataType="http://www.w3.org/2001/XMLSchema#string">REGNA</xacml:AttributeValue>
+            <xacml:AttributeDesignator AttributeId="urn:altinn:rolecode" Category="urn:oasis:names:tc:xacml:1.0:subject-category:access-subject" DataType="http://www.w3.org/2001/XMLSchema#string" MustBePresent="false"/>
+          </xacml:Match>
+        </xacml:AllOf>
+      </xacml:AnyOf>
+      <xacml:AnyOf>
+        <xacml:AllOf>
+          <xacml:Match MatchId="urn:oasis:names:tc:xacml:1.0:function:string-equal">
+            <xacml:AttributeValue DataType="http://www.w3.org/2001/XMLSchema#string">ttd</xacml:AttributeValue>
+            <xacml:AttributeDesignator AttributeId="urn:altinn:org" Category="urn:oasis:names:tc:xacml:3.0:attribute-category:resource" DataType="http://www.w3.org/2001/XMLSchema#string" MustBePresent="false"/>
+          </xacml:Match>
+          <xacml:Match MatchId="urn:oasis:names:tc:xacml:1.0:function:string-equal">
+            <xacml:AttributeValue DataType="http://www.w3.org/2001/XMLSchema#string">app</xacml:AttributeValue>
+            <xacml:AttributeDesignator AttributeId="urn:altinn:app" Category="urn:oasis:names:tc:xacml:3.0:attribute-category:resource" DataType="http://www.w3.org/2001/XMLSchema#string" MustBePresent="false"/>
+          </xacml:Match>
+        </xacml:AllOf>
+      </xacml:AnyOf>
+      <xacml:AnyOf>
+        <xacml:AllOf>
+          <xacml:Match MatchId="urn:oasis:names:tc:xacml:1.0:function:string-equal">
+            <xacml:AttributeValue DataType="http://www.w3.org/2001/XMLSchema#string">read</xacml:AttributeValue>
+            <xacml:AttributeDesignator AttributeId="urn:oasis:names:tc:xacml:1.0:action:action-id" Category="urn:oasis:names:tc:xacml:3.0:attribute-category:action" DataType="http://www.w3.org/2001/XMLSchema#string" MustBePresent="false"/>
+          </xacml:Match>
+        </xacml:AllOf>
+        <xacml:AllOf>
+          <xacml:Match MatchId="urn:oasis:names:tc:xacml:1.0:function:string-equal">
+            <xacml:AttributeValue DataType="http://www.w3.org/2001/XMLSchema#string">write</xacml:AttributeValue>
+            <xacml:AttributeDesignator AttributeId="urn:oasis:names:tc:xacml:1.0:action:action-id" Category="urn:oasis:names:tc:xacml:3.0:attribute-category:action" DataType="http://www.w3.org/2001/XMLSchema#string" MustBePresent="false"/>
+          </xacml:Match>
+        </xacml:AllOf>
+      </xacml:AnyOf>
+    </xacml:Target>
+  </xacml:Rule>
+  <xacml:Rule RuleId="urn:altinn:example:ruleid:2" Effect="Permit">
+    <xacml:Target>
+      <xacml:AnyOf>
+        <xacml:AllOf>
+          <xacml:Match MatchId="urn:oasis:names:tc:xacml:1.0:function:string-equal">
+            <xacml:AttributeValue DataType="http://www.w3.org/2001/XMLSchema#string">ttd</xacml:AttributeValue>
+            <xacml:AttributeDesignator AttributeId="urn:altinn:org" Category="urn:oasis:names:tc:xacml:3.0:attribute-category:resource" DataType="http://www.w3.org/2001/XMLSchema#string" MustBePresent="false"/>
+          </xacml:Match>
+        </xacml:AllOf>
+      </xacml:AnyOf>
+    </xacml:Target>
+  </xacml:Rule>
+  <xacml:ObligationExpressions>
+    <xacml:ObligationExpression FulfillOn="Permit" ObligationId="urn:altinn:obligation:authenticationLevel1">
+      <xacml:AttributeAssignmentExpression AttributeId="urn:altinn:obligation-assignment:1" Category="urn:altinn:minimum-authenticationlevel">
+        <xacml:AttributeValue DataType="http://www.w3.org/2001/XMLSchema#integer">2</xacml:AttributeValue>
+      </xacml:AttributeAssignmentExpression>
+    </xacml:ObligationExpression>
+  </xacml:ObligationExpressions>
+</xacml:Policy>"#;
+
+    #[test]
+    fn parses_rules_into_any_of_and_all_of_groups() {
+        let policy = parse(POLICY).unwrap();
+
+        assert_eq!(policy.policy_id.as_deref(), Some("urn:altinn:example:policyid:1"));
+        assert_eq!(policy.rules.len(), 2);
+
+        let rule = &policy.rules[0];
+        assert_eq!(rule.effect, "Permit");
+        assert_eq!(rule.description.as_deref(), Some("DAGL and REGNA can read and write"));
+        assert_eq!(rule.subjects.len(), 2);
+        assert_eq!(rule.subjects[0][0].short(), "rolecode=DAGL");
+        assert_eq!(rule.resources.len(), 1);
+        assert_eq!(rule.resources[0].len(), 2);
+        assert_eq!(
+            rule.action_names(),
+            BTreeSet::from(["read".to_string(), "write".to_string()])
+        );
+    }
+
+    #[test]
+    fn reports_permissions_and_authentication_level() {
+        let policy = parse(POLICY).unwrap();
+
+        assert_eq!(policy.authentication_level(), Some("2"));
+        let permissions = policy.permissions();
+        assert_eq!(
+            permissions["rolecode=REGNA"],
+            BTreeSet::from(["read".to_string(), "write".to_string()])
+        );
+        assert_eq!(permissions["<any>"], BTreeSet::from(["<any>".to_string()]));
+    }
+
+    #[test]
+    fn warns_about_broad_rules() {
+        let policy = parse(POLICY).unwrap();
+
+        let warnings = policy
+            .warnings()
+            .into_iter()
+            .map(|w| (w.rule.unwrap_or_default(), w.message))
+            .collect::<Vec<_>>();
+        let rule = "urn:altinn:example:ruleid:2".to_string();
+        assert_eq!(
+            warnings,
+            vec![
+                (rule.clone(), "permits any subject".to_string()),
+                (rule.clone(), "permits any action".to_string()),
+                (rule, "not scoped to the app".to_string()),
+            ]
+        );
+    }
+}
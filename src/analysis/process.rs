@@ -0,0 +1,396 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::fmt;
+
+use anyhow::Result;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use serde::{Deserialize, Serialize};
+
+use super::{print_ranking, xml_attribute as attribute, AppFiles, LocalApp};
+
+pub const PATH: &str = "App/config/process/process.bpmn";
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Process {
+    pub start_events: Vec<String>,
+    pub end_events: Vec<String>,
+    pub tasks: Vec<Task>,
+    pub gateways: Vec<Gateway>,
+    pub flows: Vec<SequenceFlow>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Task {
+    pub id: String,
+    pub name: Option<String>,
+    pub task_type: TaskType,
+    pub actions: Vec<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TaskType {
+    #[default]
+    Data,
+    Confirmation,
+    Signing,
+    Payment,
+    Feedback,
+    Custom(String),
+}
+
+impl TaskType {
+    fn parse(value: &str) -> Self {
+        match value.trim() {
+            "data" => TaskType::Data,
+            "confirmation" => TaskType::Confirmation,
+            "signing" => TaskType::Signing,
+            "payment" => TaskType::Payment,
+            "feedback" => TaskType::Feedback,
+            other => TaskType::Custom(other.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for TaskType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TaskType::Data => write!(f, "data"),
+            TaskType::Confirmation => write!(f, "confirmation"),
+            TaskType::Signing => write!(f, "signing"),
+            TaskType::Payment => write!(f, "payment"),
+            TaskType::Feedback => write!(f, "feedback"),
+            TaskType::Custom(name) => write!(f, "custom({})", name),
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Gateway {
+    pub id: String,
+    pub name: Option<String>,
+    /// Element name without the `Gateway` suffix, e.g. `exclusive` or `parallel`
+    pub kind: String,
+    pub default_flow: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SequenceFlow {
+    pub id: String,
+    pub source: String,
+    pub target: String,
+    pub condition: Option<String>,
+}
+
+impl Process {
+    /// Tasks in the order they are reached from the start event(s).
+    /// Tasks that can't be reached are placed last
+    pub fn task_sequence(&self) -> Vec<&Task> {
+        let mut outgoing = HashMap::<&str, Vec<&str>>::new();
+        for flow in &self.flows {
+            outgoing.entry(&flow.source).or_default().push(&flow.target);
+        }
+
+        let mut visited = BTreeSet::<&str>::new();
+        let mut queue = self.start_events.iter().map(|s| s.as_str()).collect::<VecDeque<_>>();
+        let mut order = Vec::new();
+        while let Some(node) = queue.pop_front() {
+            if !visited.insert(node) {
+                continue;
+            }
+            if let Some(task) = self.tasks.iter().find(|t| t.id == node) {
+                order.push(task);
+            }
+            queue.extend(outgoing.get(node).into_iter().flatten());
+        }
+
+        order.extend(self.tasks.iter().filter(|t| !visited.contains(t.id.as_str())));
+        order
+    }
+
+    /// Conditional flows going out of gateways
+    pub fn gateway_conditions(&self) -> Vec<(&Gateway, &SequenceFlow)> {
+        self.flows
+            .iter()
+            .filter_map(|flow| {
+                let gateway = self.gateways.iter().find(|g| g.id == flow.source)?;
+                flow.condition.as_ref().map(|_| (gateway, flow))
+            })
+            .collect()
+    }
+
+    pub fn task_types(&self) -> BTreeSet<&TaskType> {
+        self.tasks.iter().map(|t| &t.task_type).collect()
+    }
+}
+
+pub fn load(files: &(impl AppFiles + ?Sized)) -> Result<Option<Process>> {
+    let Some(content) = files.read_file(PATH)? else {
+        return Ok(None);
+    };
+
+    parse(&content).map(Some)
+}
+
+pub fn parse(content: &str) -> Result<Process> {
+    let mut reader = Reader::from_str(content);
+    reader.trim_text(true);
+
+    let mut process = Process::default();
+    let mut stack = Vec::<String>::new();
+
+    loop {
+        let event = reader.read_event()?;
+        let (e, empty) = match &event {
+            Event::Start(e) => (e, false),
+            Event::Empty(e) => (e, true),
+            Event::End(_) => {
+                stack.pop();
+                continue;
+            }
+            Event::Text(e) => {
+                let text = e.unescape()?.trim().to_string();
+                handle_text(&mut process, &stack, text);
+                continue;
+            }
+            Event::CData(e) => {
+                let text = String::from_utf8_lossy(e).trim().to_string();
+                handle_text(&mut process, &stack, text);
+                continue;
+            }
+            Event::Eof => break,
+            _ => continue,
+        };
+
+        let name = String::from_utf8_lossy(e.local_name().as_ref()).to_string();
+        let id = attribute(e, "id")?.unwrap_or_default();
+        match name.as_str() {
+            "startEvent" => process.start_events.push(id),
+            "endEvent" => process.end_events.push(id),
+            "task" | "userTask" | "serviceTask" | "manualTask" => {
+                // Older apps specify the type as an attribute instead of an extension element
+                let task_type = attribute(e, "tasktype")?
+                    .map(|t| TaskType::parse(&t))
+                    .unwrap_or_default();
+                process.tasks.push(Task {
+                    id,
+                    name: attribute(e, "name")?,
+                    task_type,
+                    actions: Vec::new(),
+                });
+            }
+            gateway if gateway.ends_with("Gateway") => process.gateways.push(Gateway {
+                id,
+                name: attribute(e, "name")?,
+                kind: gateway.trim_end_matches("Gateway").to_string(),
+                default_flow: attribute(e, "default")?,
+            }),
+            "sequenceFlow" => process.flows.push(SequenceFlow {
+                id,
+                source: attribute(e, "sourceRef")?.unwrap_or_default(),
+                target: attribute(e, "targetRef")?.unwrap_or_default(),
+                condition: None,
+            }),
+            _ => {}
+        }
+
+        if !empty {
+            stack.push(name);
+        }
+    }
+
+    Ok(process)
+}
+
+fn handle_text(process: &mut Process, stack: &[String], text: String) {
+    if text.is_empty() {
+        return;
+    }
+
+    let in_task = stack
+        .iter()
+        .any(|n| matches!(n.as_str(), "task" | "userTask" | "serviceTask" | "manualTask"));
+
+    match stack.last().map(|n| n.as_str()) {
+        Some("taskType") if in_task => {
+            if let Some(task) = process.tasks.last_mut() {
+                task.task_type = TaskType::parse(&text);
+            }
+        }
+        Some("action") if in_task => {
+            if let Some(task) = process.tasks.last_mut() {
+                task.actions.push(text);
+            }
+        }
+        Some("conditionExpression") => {
+            if let Some(flow) = process.flows.last_mut() {
+                flow.condition = Some(text);
+            }
+        }
+        _ => {}
+    }
+}
+
+pub fn report(apps: &[LocalApp]) -> Result<()> {
+    let mut type_apps = BTreeMap::<String, usize>::new();
+    let mut task_counts = BTreeMap::<String, usize>::new();
+    let mut with_gateways = 0;
+    let mut missing = Vec::new();
+    let mut failed = Vec::new();
+
+    for app in apps {
+        let process = match load(&app.dir) {
+            Ok(Some(process)) => process,
+            Ok(None) => {
+                missing.push(app);
+                continue;
+            }
+            Err(err) => {
+                failed.push((app, err));
+                continue;
+            }
+        };
+
+        let sequence = process
+            .task_sequence()
+            .iter()
+            .map(|t| format!("{}:{}", t.id, t.task_type))
+            .collect::<Vec<_>>()
+            .join(" -> ");
+        println!("{:<60} gateways={:<2} {}", app.key(), process.gateways.len(), sequence);
+        for (gateway, flow) in process.gateway_conditions() {
+            println!(
+                "  {} -> {}: {}",
+                gateway.id,
+                flow.target,
+                flow.condition.as_deref().unwrap_or_default()
+            );
+        }
+
+        // All custom task types are counted together, but only once per app
+        let app_types = process
+            .task_types()
+            .into_iter()
+            .map(|task_type| match task_type {
+                TaskType::Custom(_) => "custom".to_string(),
+                t => t.to_string(),
+            })
+            .collect::<BTreeSet<_>>();
+        for key in app_types {
+            *type_apps.entry(key).or_default() += 1;
+        }
+        *task_counts.entry(format!("{} tasks", process.tasks.len())).or_default() += 1;
+        if !process.gateways.is_empty() {
+            with_gateways += 1;
+        }
+    }
+
+    println!("--------------------------------------------------");
+    print_ranking("Task types (number of apps)", &type_apps);
+    print_ranking("Number of tasks (number of apps)", &task_counts);
+    println!("Apps with gateways: {}", with_gateways);
+
+    println!("--------------------------------------------------");
+    println!("Missing {} ({}):", PATH, missing.len());
+    for app in &missing {
+        println!("  {}", app.key());
+    }
+
+    println!("Failed to parse {} ({}):", PATH, failed.len());
+    for (app, err) in &failed {
+        println!("  {}: {:#}", app.key(), err);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROCESS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<bpmn:definitions xmlns:bpmn="http://www.omg.org/spec/BPMN/20100524/MODEL" xmlns:altinn="http://altinn.no/process">
+  <bpmn:process id="Process_1" isExecutable="false">
+    <bpmn:startEvent id="StartEvent_1" />
+    <bpmn:sequenceFlow id="Flow_1" sourceRef="StartEvent_1" targetRef="Task_1" />
+    <bpmn:task id="Task_2" name="Signering">
+      <bpmn:extensionElements>
+        <altinn:taskExtension>
+          <altinn:taskType>signing</altinn:taskType>
+          <altinn:actions>
+            <altinn:action>sign</altinn:action>
+            <altinn:action>reject</altinn:action>
+          </altinn:actions>
+        </altinn:taskExtension>
+      </bpmn:extensionElements>
+    </bpmn:task>
+    <bpmn:task id="Task_1" name="Utfylling" altinn:tasktype="data" />
+    <bpmn:sequenceFlow id="Flow_2" sourceRef="Task_1" targetRef="Gateway_1" />
+    <bpmn:exclusiveGateway id="Gateway_1" default="Flow_4" />
+    <bpmn:sequenceFlow id="Flow_3" sourceRef="Gateway_1" targetRef="Task_2">
+      <bpmn:conditionExpression>["equals", ["dataModel", "sign"], true]</bpmn:conditionExpression>
+    </bpmn:sequenceFlow>
+    <bpmn:sequenceFlow id="Flow_4" sourceRef="Gateway_1" targetRef="EndEvent_1" />
+    <bpmn:sequenceFlow id="Flow_5" sourceRef="Task_2" targetRef="EndEvent_1" />
+    <bpmn:task id="Task_3">
+      <bpmn:extensionElements>
+        <altinn:taskExtension>
+          <altinn:taskType>eFormidling</altinn:taskType>
+        </altinn:taskExtension>
+      </bpmn:extensionElements>
+    </bpmn:task>
+    <bpmn:endEvent id="EndEvent_1" />
+  </bpmn:process>
+</bpmn:definitions>"#;
+
+    #[test]
+    fn parses_tasks_from_attributes_and_extension_elements() {
+        let process = parse(PROCESS).unwrap();
+
+        assert_eq!(process.start_events, vec!["StartEvent_1"]);
+        assert_eq!(process.end_events, vec!["EndEvent_1"]);
+        let tasks = process
+            .tasks
+            .iter()
+            .map(|t| (t.id.as_str(), t.task_type.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            tasks,
+            vec![
+                ("Task_2", TaskType::Signing),
+                ("Task_1", TaskType::Data),
+                ("Task_3", TaskType::Custom("eFormidling".to_string())),
+            ]
+        );
+        assert_eq!(process.tasks[0].actions, vec!["sign", "reject"]);
+    }
+
+    #[test]
+    fn parses_gateways_and_conditions() {
+        let process = parse(PROCESS).unwrap();
+
+        assert_eq!(process.gateways.len(), 1);
+        assert_eq!(process.gateways[0].kind, "exclusive");
+        assert_eq!(process.gateways[0].default_flow.as_deref(), Some("Flow_4"));
+
+        let conditions = process.gateway_conditions();
+        assert_eq!(conditions.len(), 1);
+        assert_eq!(conditions[0].1.target, "Task_2");
+        assert_eq!(
+            conditions[0].1.condition.as_deref(),
+            Some(r#"["equals", ["dataModel", "sign"], true]"#)
+        );
+    }
+
+    #[test]
+    fn orders_tasks_by_flow_with_unreachable_tasks_last() {
+        let process = parse(PROCESS).unwrap();
+
+        let sequence = process
+            .task_sequence()
+            .iter()
+            .map(|t| t.id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(sequence, vec!["Task_1", "Task_2", "Task_3"]);
+    }
+}
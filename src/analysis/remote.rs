@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use tokio::runtime::Handle;
+
+use super::AppFiles;
+use crate::gitea_client::{GiteaClient, GiteaRepo};
+
+/// Files of an app on the default branch in Gitea. The full list of paths is known, and the content
+/// of the files that were asked for when fetching is kept. Other files are fetched when they are read
+pub struct RemoteFiles {
+    client: GiteaClient,
+    repo: GiteaRepo,
+    runtime: Handle,
+    paths: Vec<String>,
+    files: HashMap<String, String>,
+}
+
+/// Fetches the file tree of the repo and the content of the files matching the predicate
+pub async fn fetch(client: &GiteaClient, repo: &GiteaRepo, wanted: &dyn Fn(&str) -> bool) -> Result<RemoteFiles> {
+    let paths = client.get_files(repo).await?;
+
+    let mut files = HashMap::new();
+    for path in paths.iter().filter(|p| wanted(p)) {
+        if let Some(content) = client.get_file(repo, path).await? {
+            files.insert(path.clone(), content);
+        }
+    }
+
+    Ok(RemoteFiles {
+        client: client.clone(),
+        repo: repo.clone(),
+        runtime: Handle::current(),
+        paths,
+        files,
+    })
+}
+
+impl AppFiles for RemoteFiles {
+    fn read_file(&self, path: &str) -> Result<Option<String>> {
+        let content = match self.files.get(path) {
+            Some(content) => Some(content.clone()),
+            // Analyzers run on blocking threads, so waiting for the request here doesn't stall the runtime
+            None if self.paths.iter().any(|p| p == path) => self
+                .runtime
+                .block_on(self.client.get_file(&self.repo, path))
+                .with_context(|| format!("Failed to fetch {} from Gitea", path))?,
+            None => None,
+        };
+
+        // Some files are saved with a BOM by Windows editors
+        Ok(content.map(|c| c.trim_start_matches('\u{feff}').to_string()))
+    }
+
+    fn list_files(&self, dir: &str) -> Result<Vec<String>> {
+        Ok(self
+            .paths
+            .iter()
+            .filter(|p| p.rsplit_once('/').is_some_and(|(parent, _)| parent == dir))
+            .cloned()
+            .collect())
+    }
+
+    fn find_files(&self, predicate: &dyn Fn(&str) -> bool) -> Result<Vec<String>> {
+        Ok(self.paths.iter().filter(|p| predicate(p)).cloned().collect())
+    }
+}
@@ -0,0 +1,387 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::Utc;
+use futures::future::join_all;
+use futures::{stream, StreamExt};
+use git2::Repository;
+use spmc::Receiver;
+use tokio::task::{spawn_blocking, JoinError};
+
+use super::analyzer::{self, Analyzer, AppHandle, Finding, Findings, Report, Severity};
+use super::{discover, print_ranking, remote, LocalApp};
+use crate::cdn_client::{CdnClient, CdnOrganization};
+use crate::configuration::Configuration;
+use crate::deployment::{self, Deployment};
+use crate::gitea_client::{GiteaClient, GiteaOrganization, GiteaRepo};
+use crate::ui::Ui;
+use crate::{inventory, snapshot};
+
+pub async fn run(
+    config: &'static Configuration,
+    names: &[String],
+    orgs: &[String],
+    output: Option<&Path>,
+    offline: bool,
+    remote: bool,
+    deployed: &[Deployment],
+) -> Result<()> {
+    if offline && !deployed.is_empty() {
+        bail!("--deployed needs Gitea and Altinn Storage, and can't be combined with --offline");
+    }
+    let analyzers = select(names)?;
+
+    println!(
+        "Analyzing: {}",
+        if remote {
+            config.base_url.to_string()
+        } else {
+            config.dir.display().to_string()
+        }
+    );
+    println!(
+        "Analyzers: {}",
+        analyzers.iter().map(|a| a.name()).collect::<Vec<_>>().join(", ")
+    );
+
+    let (handles, upstream) = if remote {
+        (remote_handles(config, orgs, &analyzers, deployed).await?, None)
+    } else {
+        let (handles, upstream) = local_handles(config, orgs, offline, deployed).await?;
+        (handles, Some(upstream))
+    };
+
+    println!("Number of repos: {}", handles.len());
+    println!("--------------------------------------------------");
+
+    let report = analyze(handles, analyzers).await?;
+
+    print_report(&report);
+
+    if let Some(output) = output {
+        let json = serde_json::to_string_pretty(&report).context("Failed to serialize report")?;
+        tokio::fs::write(output, json)
+            .await
+            .with_context(|| format!("Failed to write report to {}", output.display()))?;
+        println!("Wrote report to {}", output.display());
+    }
+
+    // Snapshots are compared app by app, so one of only some apps or analyzers would show the rest as removed
+    let upstream = upstream.filter(|_| names.is_empty() && orgs.is_empty() && deployed.is_empty());
+    let Some(upstream) = upstream else {
+        println!("Not saving a snapshot, since the report doesn't cover all cloned apps and analyzers");
+        return Ok(());
+    };
+    let inventory = inventory::from_report(config, upstream, report).await?;
+    let path = spawn_blocking(move || snapshot::save(&config.dir, &inventory))
+        .await
+        .context("Failed to wait for saving snapshot")??;
+    println!("Saved snapshot to {}", path.display());
+
+    Ok(())
+}
+
+/// Orgs and repos as registered in Gitea and the CDN, which are all empty when offline
+#[derive(Default)]
+pub struct Upstream {
+    pub gitea_orgs: Vec<GiteaOrganization>,
+    /// Keyed by `org/name`, like the cloned apps
+    pub repos: BTreeMap<String, GiteaRepo>,
+    pub cdn_orgs: HashMap<String, CdnOrganization>,
+}
+
+impl Upstream {
+    pub async fn fetch(config: &'static Configuration, offline: bool) -> Result<Self> {
+        if offline {
+            return Ok(Self::default());
+        }
+
+        let gitea_client = GiteaClient::new(config);
+        let gitea_orgs = gitea_client.get_orgs().await?;
+        let repos = gitea_client
+            .get_all_repos(&gitea_orgs)
+            .await?
+            .into_iter()
+            .filter_map(|r| {
+                let key = r.org_and_name().map(|(org, name)| format!("{}/{}", org, name))?;
+                Some((key, r))
+            })
+            .collect();
+        let cdn_orgs = CdnClient::new().get_orgs().await?.orgs;
+
+        Ok(Upstream {
+            gitea_orgs,
+            repos,
+            cdn_orgs,
+        })
+    }
+}
+
+/// Handles for the repos cloned into the directory, with details from Gitea and the CDN unless offline.
+/// The fetched details are returned as well, so that they can be reused for the snapshot
+async fn local_handles(
+    config: &'static Configuration,
+    orgs: &[String],
+    offline: bool,
+    deployed: &[Deployment],
+) -> Result<(Vec<AppHandle>, Upstream)> {
+    let dir = config.dir.clone();
+    let mut apps = spawn_blocking(move || discover(&dir))
+        .await
+        .context("Failed to wait for discovery")??;
+    apps.retain(|app| orgs.is_empty() || orgs.contains(&app.org));
+    let apps = deployment::filter_apps(config, apps, deployed).await?;
+
+    let upstream = Upstream::fetch(config, offline).await?;
+
+    let mut not_in_gitea = 0;
+    let handles = apps
+        .into_iter()
+        .map(|app| {
+            let repo = upstream.repos.get(&app.key()).cloned().unwrap_or_else(|| {
+                not_in_gitea += 1;
+                app.gitea_repo(&config.base_url)
+            });
+            AppHandle {
+                org: upstream.cdn_orgs.get(&app.org).cloned(),
+                repo,
+                files: Box::new(app.dir.clone()),
+                app,
+                git: None,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if !offline && not_in_gitea > 0 {
+        println!("Repos not found in Gitea: {}", not_in_gitea);
+    }
+
+    Ok((handles, upstream))
+}
+
+/// Handles for all repos in Gitea, with only the files the analyzers need fetched from the default branch
+async fn remote_handles(
+    config: &'static Configuration,
+    orgs: &[String],
+    analyzers: &[Box<dyn Analyzer>],
+    deployed: &[Deployment],
+) -> Result<Vec<AppHandle>> {
+    const CONCURRENCY: usize = 16;
+
+    let gitea_client = GiteaClient::new(config);
+    let mut gitea_orgs = gitea_client.get_orgs().await?;
+    gitea_orgs.retain(|o| orgs.is_empty() || o.name.as_ref().is_some_and(|n| orgs.contains(n)));
+    let repos = gitea_client.get_all_repos(&gitea_orgs).await?;
+    let cdn_orgs = CdnClient::new().get_orgs().await?.orgs;
+    let repos = deployment::filter_repos(config, &gitea_client, repos, deployed).await?;
+
+    let total = repos.len() as u64;
+    let done = AtomicU64::new(0);
+    let (ui, ui_thread) = Ui::new();
+    let wanted = |path: &str| analyzers.iter().any(|a| a.wants(path));
+
+    let results = stream::iter(repos)
+        .map(|repo| {
+            let (gitea_client, ui, done, wanted) = (&gitea_client, &ui, &done, &wanted);
+            async move {
+                let files = remote::fetch(gitea_client, &repo, wanted).await;
+                let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+                ui.update("Fetching files from Gitea", done, total);
+                (repo, files)
+            }
+        })
+        .buffer_unordered(CONCURRENCY)
+        .collect::<Vec<_>>()
+        .await;
+
+    drop(ui);
+
+    ui_thread.await.context("Failed to wait for UI thread")?;
+    println!();
+
+    let mut handles = Vec::with_capacity(results.len());
+    let mut failed = Vec::new();
+    for (repo, files) in results {
+        let Some((org, name)) = repo.org_and_name() else {
+            continue;
+        };
+        let app = LocalApp {
+            org: org.to_string(),
+            name: name.to_string(),
+            dir: config.dir.join(org).join(name),
+        };
+        match files {
+            Ok(files) => handles.push(AppHandle {
+                org: cdn_orgs.get(&app.org).cloned(),
+                files: Box::new(files),
+                app,
+                repo,
+                git: None,
+            }),
+            Err(err) => failed.push((app, err)),
+        }
+    }
+    handles.sort_by(|a, b| a.app.org.cmp(&b.app.org).then_with(|| a.app.name.cmp(&b.app.name)));
+
+    if !failed.is_empty() {
+        println!("Failed to fetch from Gitea ({}):", failed.len());
+        for (app, err) in &failed {
+            println!("  {}: {:#}", app.key(), err);
+        }
+    }
+
+    Ok(handles)
+}
+
+/// Selects analyzers by name, or all builtin analyzers if no names are given
+pub fn select(names: &[String]) -> Result<Vec<Box<dyn Analyzer>>> {
+    let mut analyzers = analyzer::builtin();
+    if names.is_empty() {
+        return Ok(analyzers);
+    }
+
+    for name in names {
+        if !analyzers.iter().any(|a| a.name() == name) {
+            let available = analyzers.iter().map(|a| a.name()).collect::<Vec<_>>();
+            bail!("Unknown analyzer {} - available: {}", name, available.join(", "));
+        }
+    }
+    analyzers.retain(|a| names.iter().any(|n| n == a.name()));
+
+    Ok(analyzers)
+}
+
+/// Runs all analyzers against all apps in parallel and merges the findings into a single report.
+/// Failing analyzers are reported as errors for that app instead of failing the run
+pub async fn analyze(apps: Vec<AppHandle>, analyzers: Vec<Box<dyn Analyzer>>) -> Result<Report> {
+    let total = apps.len() as u64;
+    let keys = apps.iter().map(|h| h.app.key()).collect::<Vec<_>>();
+    let analyzers = Arc::new(analyzers);
+    let done = Arc::new(AtomicU64::new(0));
+
+    let (mut tx, rx) = spmc::channel::<AppHandle>();
+
+    let (ui, ui_thread) = Ui::new();
+
+    let mut threads = Vec::with_capacity(num_cpus::get());
+    for _ in 0..num_cpus::get() {
+        let rx = rx.clone();
+        let ui = ui.clone();
+        let analyzers = analyzers.clone();
+        let done = done.clone();
+
+        threads.push(spawn_blocking(move || thread(rx, &analyzers, &ui, &done, total)));
+    }
+
+    for app in apps {
+        tx.send(app).map_err(|_| anyhow!("Failed to queue app"))?;
+    }
+
+    drop(tx);
+
+    let results = join_all(threads)
+        .await
+        .into_iter()
+        .collect::<Result<Vec<_>, JoinError>>()?;
+
+    drop(ui);
+
+    ui_thread.await.context("Failed to wait for UI thread")?;
+    println!();
+
+    let mut findings = results.into_iter().flatten().collect::<Vec<_>>();
+    // Threads finish apps in any order, but findings within an app keep the order of the analyzers
+    findings.sort_by(|a, b| a.org.cmp(&b.org).then_with(|| a.app.cmp(&b.app)));
+
+    Ok(Report {
+        created: Utc::now(),
+        analyzers: analyzers.iter().map(|a| a.name().to_string()).collect(),
+        apps: keys,
+        findings,
+    })
+}
+
+fn thread(
+    rx: Receiver<AppHandle>,
+    analyzers: &[Box<dyn Analyzer>],
+    ui: &Ui,
+    done: &AtomicU64,
+    total: u64,
+) -> Vec<Finding> {
+    let mut result = Vec::new();
+
+    while let Ok(mut app) = rx.recv() {
+        if app.app.dir.is_dir() {
+            app.git = Repository::open(&app.app.dir).ok();
+        }
+
+        for analyzer in analyzers {
+            let mut findings = Findings::new(&app.app, analyzer.name());
+            if let Err(err) = analyzer.analyze(&app, &mut findings) {
+                findings.error("failed", analyzer.name(), Some(&format!("{:#}", err)));
+            }
+            result.extend(findings.into_inner());
+        }
+
+        let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+        ui.update("Analyzing apps", done, total);
+    }
+
+    result
+}
+
+fn print_report(report: &Report) {
+    let mut by_app = BTreeMap::<String, Vec<&Finding>>::new();
+    for finding in &report.findings {
+        by_app.entry(finding.key()).or_default().push(finding);
+    }
+
+    let mut kinds = BTreeMap::<String, usize>::new();
+    let mut warnings = BTreeMap::<String, usize>::new();
+    let mut errors = Vec::new();
+
+    for key in &report.apps {
+        let findings = by_app.get(key).map(|f| f.as_slice()).unwrap_or_default();
+        let count = |severity| findings.iter().filter(|f| f.severity == severity).count();
+        println!(
+            "{:<60} findings={:<5} warnings={:<3} errors={}",
+            key,
+            findings.len(),
+            count(Severity::Warning),
+            count(Severity::Error)
+        );
+
+        let app_warnings = findings
+            .iter()
+            .filter(|f| f.severity == Severity::Warning)
+            .map(|f| format!("{}/{}", f.analyzer, f.kind))
+            .collect::<BTreeSet<_>>();
+
+        for finding in findings {
+            *kinds
+                .entry(format!("{}/{}", finding.analyzer, finding.kind))
+                .or_default() += 1;
+            match finding.severity {
+                Severity::Info => {}
+                Severity::Warning => println!("  WARNING: {}", finding),
+                Severity::Error => errors.push(*finding),
+            }
+        }
+        for warning in app_warnings {
+            *warnings.entry(warning).or_default() += 1;
+        }
+    }
+
+    println!("--------------------------------------------------");
+    print_ranking("Findings by analyzer/kind", &kinds);
+    print_ranking("Warnings (number of apps)", &warnings);
+
+    println!("--------------------------------------------------");
+    println!("Errors ({}):", errors.len());
+    for finding in &errors {
+        println!("  {}: {}", finding.key(), finding);
+    }
+}
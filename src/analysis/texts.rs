@@ -0,0 +1,315 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::layouts::AppLayouts;
+use super::{layouts, print_ranking, AppFiles, LocalApp};
+
+pub const TEXTS_DIR: &str = "App/config/texts";
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextResourceFile {
+    pub language: Option<String>,
+    #[serde(default)]
+    pub resources: Vec<TextResource>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextResource {
+    pub id: String,
+    #[serde(default)]
+    pub value: String,
+    pub variables: Option<Vec<Value>>,
+}
+
+/// Text resources of an app keyed by language, then by text resource id
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppTexts {
+    pub languages: BTreeMap<String, BTreeMap<String, String>>,
+    pub errors: Vec<String>,
+}
+
+impl AppTexts {
+    pub fn keys(&self) -> BTreeSet<&str> {
+        self.languages
+            .values()
+            .flat_map(|r| r.keys())
+            .map(|k| k.as_str())
+            .collect()
+    }
+
+    /// Language -> keys that exist in other languages but not this one
+    pub fn missing_translations(&self) -> BTreeMap<&str, Vec<&str>> {
+        let keys = self.keys();
+
+        self.languages
+            .iter()
+            .map(|(language, resources)| {
+                let missing = keys.iter().filter(|k| !resources.contains_key(**k)).copied().collect();
+                (language.as_str(), missing)
+            })
+            .filter(|(_, missing): &(&str, Vec<&str>)| !missing.is_empty())
+            .collect()
+    }
+
+    /// Language -> keys with empty values
+    pub fn empty_values(&self) -> BTreeMap<&str, Vec<&str>> {
+        self.languages
+            .iter()
+            .map(|(language, resources)| {
+                let empty = resources
+                    .iter()
+                    .filter(|(_, v)| v.trim().is_empty())
+                    .map(|(k, _)| k.as_str())
+                    .collect();
+                (language.as_str(), empty)
+            })
+            .filter(|(_, empty): &(&str, Vec<&str>)| !empty.is_empty())
+            .collect()
+    }
+
+    /// Text resource keys referenced from `textResourceBindings` in layouts that don't exist in any language
+    pub fn unknown_references<'a>(&self, layouts: &'a AppLayouts) -> BTreeSet<&'a str> {
+        let keys = self.keys();
+        let namespaces = keys.iter().filter_map(|k| namespace(k)).collect::<BTreeSet<_>>();
+
+        layouts
+            .components()
+            .filter_map(|(_, _, c)| c.properties.get("textResourceBindings")?.as_object())
+            .flat_map(|bindings| bindings.values())
+            .filter_map(|v| v.as_str())
+            .filter(|v| !keys.contains(v))
+            .filter(|v| is_reference(v, &namespaces))
+            .collect()
+    }
+}
+
+/// Bindings are resolved against the text resource ids like the frontend does, showing the ones that don't
+/// resolve as literal text, e.g. `"title": "Navn"` or `"title": "e-post"`. So a binding that doesn't resolve
+/// is only taken as a reference to a missing key if it shares its namespace with one of the ids, e.g.
+/// `page1.name.description` next to `page1.name.title`, or if it's a camel case identifier like `appName`
+fn is_reference(value: &str, namespaces: &BTreeSet<&str>) -> bool {
+    if value.is_empty() || value.contains(char::is_whitespace) {
+        return false;
+    }
+
+    let camel_case = value.chars().all(|c| c.is_ascii_alphanumeric())
+        && value
+            .as_bytes()
+            .windows(2)
+            .any(|w| w[0].is_ascii_lowercase() && w[1].is_ascii_uppercase());
+
+    camel_case || namespace(value).is_some_and(|n| namespaces.contains(n))
+}
+
+/// The part of a key before its last `.` or `_`, e.g. `page1.name` for `page1.name.title`
+fn namespace(key: &str) -> Option<&str> {
+    key.rsplit_once(['.', '_']).map(|(namespace, _)| namespace)
+}
+
+pub fn load(files: &(impl AppFiles + ?Sized)) -> Result<AppTexts> {
+    let mut result = AppTexts::default();
+
+    for path in files.list_files(TEXTS_DIR)? {
+        let Some(file_language) = path
+            .rsplit('/')
+            .next()
+            .and_then(|n| n.strip_prefix("resource."))
+            .and_then(|n| n.strip_suffix(".json"))
+        else {
+            continue;
+        };
+        let Some(content) = files.read_file(&path)? else {
+            continue;
+        };
+
+        let file = match serde_json::from_str::<TextResourceFile>(&content) {
+            Ok(file) => file,
+            Err(e) => {
+                result.errors.push(format!("{}: {}", path, e));
+                continue;
+            }
+        };
+
+        let language = file.language.unwrap_or_else(|| file_language.to_string());
+        let resources = result.languages.entry(language).or_default();
+        for resource in file.resources {
+            resources.insert(resource.id, resource.value);
+        }
+    }
+
+    Ok(result)
+}
+
+pub fn report(apps: &[LocalApp]) -> Result<()> {
+    let mut languages = BTreeMap::<String, usize>::new();
+    let mut with_missing = 0;
+    let mut with_empty = 0;
+    let mut with_unknown = 0;
+    let mut no_texts = Vec::new();
+    let mut failed = Vec::new();
+    let mut layout_failed = Vec::new();
+
+    for app in apps {
+        let texts = match load(&app.dir) {
+            Ok(texts) => texts,
+            Err(err) => {
+                failed.push((app, format!("{:#}", err)));
+                continue;
+            }
+        };
+        for err in &texts.errors {
+            failed.push((app, err.clone()));
+        }
+
+        if texts.languages.is_empty() {
+            no_texts.push(app);
+            continue;
+        }
+
+        // References are only checked in layouts that could be read, the rest of the report doesn't need them
+        let layouts = match layouts::load(&app.dir) {
+            Ok(layouts) => layouts,
+            Err(err) => {
+                layout_failed.push((app, format!("{:#}", err)));
+                AppLayouts::default()
+            }
+        };
+        for err in &layouts.errors {
+            layout_failed.push((app, err.clone()));
+        }
+
+        let missing = texts.missing_translations();
+        let empty = texts.empty_values();
+        let unknown = texts.unknown_references(&layouts);
+
+        println!(
+            "{:<60} languages={:<12} keys={:<5} missing={:<5} empty={:<5} unknownRefs={}",
+            app.key(),
+            texts.languages.keys().cloned().collect::<Vec<_>>().join(","),
+            texts.keys().len(),
+            missing.values().map(|k| k.len()).sum::<usize>(),
+            empty.values().map(|k| k.len()).sum::<usize>(),
+            unknown.len()
+        );
+        for (language, keys) in &missing {
+            println!("  missing in {}: {}", language, keys.join(", "));
+        }
+        for (language, keys) in &empty {
+            println!("  empty in {}: {}", language, keys.join(", "));
+        }
+        if !unknown.is_empty() {
+            println!(
+                "  referenced from layouts but not defined: {}",
+                unknown.iter().copied().collect::<Vec<_>>().join(", ")
+            );
+        }
+
+        for language in texts.languages.keys() {
+            *languages.entry(language.clone()).or_default() += 1;
+        }
+        with_missing += usize::from(!missing.is_empty());
+        with_empty += usize::from(!empty.is_empty());
+        with_unknown += usize::from(!unknown.is_empty());
+    }
+
+    println!("--------------------------------------------------");
+    print_ranking("Languages (number of apps)", &languages);
+    println!("Apps with missing translations: {}", with_missing);
+    println!("Apps with empty values: {}", with_empty);
+    println!("Apps with undefined text resource references: {}", with_unknown);
+
+    println!("--------------------------------------------------");
+    println!("No text resources in {} ({}):", TEXTS_DIR, no_texts.len());
+    for app in &no_texts {
+        println!("  {}", app.key());
+    }
+
+    println!("Failed to parse text resources ({}):", failed.len());
+    for (app, err) in &failed {
+        println!("  {}: {}", app.key(), err);
+    }
+
+    println!(
+        "Failed to parse layouts, references not checked ({}):",
+        layout_failed.len()
+    );
+    for (app, err) in &layout_failed {
+        println!("  {}: {}", app.key(), err);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::layouts::{Component, LayoutSet, Page};
+
+    fn texts(keys: &[&str]) -> AppTexts {
+        AppTexts {
+            languages: BTreeMap::from([(
+                "nb".to_string(),
+                keys.iter().map(|k| (k.to_string(), "Tekst".to_string())).collect(),
+            )]),
+            errors: Vec::new(),
+        }
+    }
+
+    fn layouts(bindings: &str) -> AppLayouts {
+        let component = serde_json::from_str::<Component>(&format!(
+            r#"{{ "id": "name", "type": "Input", "textResourceBindings": {} }}"#,
+            bindings
+        ))
+        .unwrap();
+        AppLayouts {
+            sets: vec![LayoutSet {
+                pages: vec![Page {
+                    components: vec![component],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+            errors: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn reports_bindings_in_the_namespace_of_existing_keys() {
+        let texts = texts(&["page1.name.title"]);
+        let layouts = layouts(
+            r#"{
+                "title": "page1.name.title",
+                "description": "page1.name.description",
+                "help": "Navn",
+                "tableTitle": "Fullt navn",
+                "link": "altinn.no"
+            }"#,
+        );
+
+        assert_eq!(
+            texts.unknown_references(&layouts),
+            BTreeSet::from(["page1.name.description"])
+        );
+    }
+
+    #[test]
+    fn resolves_keys_without_separators() {
+        let texts = texts(&["appName"]);
+        let layouts = layouts(r#"{ "title": "appName", "description": "appDescription" }"#);
+
+        assert_eq!(texts.unknown_references(&layouts), BTreeSet::from(["appDescription"]));
+    }
+
+    #[test]
+    fn ordinary_text_is_not_a_missing_key() {
+        let texts = texts(&["e_post.title"]);
+        let layouts = layouts(r#"{ "title": "e-post", "description": "E-post", "help": "OK" }"#);
+
+        assert!(texts.unknown_references(&layouts).is_empty());
+    }
+}
@@ -1,10 +1,12 @@
 use std::path::PathBuf;
 
 use anyhow::{anyhow, Context, Result};
-use clap::{arg, Parser};
+use clap::{Parser, Subcommand, ValueEnum};
 use config::Config;
 use reqwest::Url;
 
+use crate::deployment::Deployment;
+
 /// Utility for cloning all Altinn apps
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
@@ -17,6 +19,14 @@ struct Args {
     #[arg(long = "url")]
     base_url: Option<String>,
 
+    /// Url of the Altinn platform in tt02, where apps deployed there are registered. Derived from the base url
+    #[arg(long = "tt02-url")]
+    tt02_url: Option<String>,
+
+    /// Url of the Altinn platform in production, where apps deployed there are registered. Derived from the base url
+    #[arg(long = "production-url")]
+    production_url: Option<String>,
+
     /// Username for authentication
     #[arg(short, long)]
     username: Option<String>,
@@ -24,13 +34,257 @@ struct Args {
     /// Password for authentication (token from Gitea)
     #[arg(short, long)]
     password: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Clone all app repos into the directory (default)
+    Clone {
+        /// Only clone apps deployed to these environments, as registered in Altinn Storage
+        #[arg(long = "deployed", value_enum)]
+        deployed: Vec<Deployment>,
+
+        /// Check out this branch in the repos where it exists, and the default branch in the others
+        #[arg(long)]
+        branch: Option<String>,
+    },
+
+    /// Analyze app repos that have already been cloned into the directory
+    Analyze {
+        #[command(subcommand)]
+        analysis: Analysis,
+
+        /// Only analyze apps deployed to these environments, as registered in Altinn Storage
+        #[arg(long = "deployed", value_enum, global = true)]
+        deployed: Vec<Deployment>,
+    },
+
+    /// Search the files of all cloned repos with a regex, respecting .gitignore and skipping binary files
+    Search {
+        /// Regex to search for, e.g. `IDataProcessor|IDataWriteProcessor`
+        pattern: String,
+
+        /// Case insensitive search
+        #[arg(short, long)]
+        ignore_case: bool,
+
+        /// Only search apps in these orgs
+        #[arg(long = "org")]
+        orgs: Vec<String>,
+
+        /// Only search files matching these globs, e.g. `--glob '*.cs'`
+        #[arg(short, long = "glob")]
+        globs: Vec<String>,
+
+        /// Only search files under this path in each app, e.g. `App/logic`
+        #[arg(long)]
+        path: Option<String>,
+
+        /// Search all files directly instead of using the search index
+        #[arg(long)]
+        no_index: bool,
+
+        /// Link to matches using the repos as Gitea knows them, instead of deriving the links from the base url
+        #[arg(long)]
+        gitea_links: bool,
+    },
+
+    /// Build or update the search index of the cloned repos, which is otherwise done on every search
+    Index,
+
+    /// Evaluate a JSONPath expression against the JSON config files of all cloned repos,
+    /// e.g. `$.dataTypes[?@.maxCount > 1]` or `$.data.layout[?@.type == 'Group' && @.maxCount]`
+    Query {
+        /// JSONPath expression (RFC 9535)
+        expression: String,
+
+        /// Only query these files (default: all)
+        #[arg(long = "file", value_enum)]
+        files: Vec<QueryFile>,
+
+        /// Only query apps in these orgs
+        #[arg(long = "org")]
+        orgs: Vec<String>,
+    },
+
+    /// Export orgs, repos, latest commits and the findings of all analyzers for use in other tools (at least one output is required)
+    Export {
+        /// Write everything into normalized tables in a new SQLite database at this path
+        #[arg(long)]
+        sqlite: Option<PathBuf>,
+
+        /// Write `orgs.csv`, `repos.csv` and `findings.csv` into this directory
+        #[arg(long)]
+        csv: Option<PathBuf>,
+
+        /// Write `orgs.jsonl`, `repos.jsonl` and `findings.jsonl` into this directory
+        #[arg(long)]
+        jsonl: Option<PathBuf>,
+
+        /// Write a self-contained HTML report with sortable tables and a page per app into this directory
+        #[arg(long)]
+        html: Option<PathBuf>,
+
+        /// Don't fetch repo and org details from Gitea and the CDN, only export what has been cloned
+        #[arg(long)]
+        offline: bool,
+
+        /// Only export apps deployed to these environments, as registered in Altinn Storage.
+        /// The snapshot that is stored on every export still includes all apps
+        #[arg(long = "deployed", value_enum, conflicts_with = "offline")]
+        deployed: Vec<Deployment>,
+    },
+
+    /// Store the inventory and the findings of all analyzers as a dated snapshot in `<dir>/.snapshots`,
+    /// which is also done on every export and on every `analyze report` of all cloned apps with all analyzers
+    Snapshot {
+        /// Don't fetch repo and org details from Gitea and the CDN, only include what has been cloned
+        #[arg(long)]
+        offline: bool,
+    },
+
+    /// Compare two snapshots: new and deleted orgs and apps, version upgrades and newly adopted components
+    Diff {
+        /// Snapshot to compare from, as a path or the start of its name, e.g. `2024-05-01` (default: the one before `to`)
+        from: Option<String>,
+
+        /// Snapshot to compare to (default: the latest)
+        to: Option<String>,
+    },
+
+    /// Run a shell command in every cloned repo in parallel, e.g. `exec -- dotnet build App`.
+    /// The org and name of the repo are available to the command as `$ALTINN_ORG` and `$ALTINN_APP`
+    Exec {
+        /// Command to run with `sh -c` (`cmd /C` on Windows), with the repo dir as working directory. A single argument
+        /// is run as a shell command line, while multiple arguments are quoted and run as a single command
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+
+        /// Only run in repos in these orgs
+        #[arg(long = "org")]
+        orgs: Vec<String>,
+
+        /// Only run in these repos, as `name` or `org/name`
+        #[arg(long = "repo")]
+        repos: Vec<String>,
+
+        /// Number of repos to run the command in at the same time (default: number of CPUs)
+        #[arg(short, long)]
+        jobs: Option<usize>,
+
+        /// Print the output of every repo, not just the ones where the command failed
+        #[arg(short, long)]
+        verbose: bool,
+    },
+}
+
+impl Command {
+    /// Whether the command talks to Gitea, which requires credentials
+    pub fn uses_gitea(&self) -> bool {
+        match self {
+            Command::Clone { .. } => true,
+            Command::Analyze {
+                analysis: Analysis::Report { offline, .. },
+                deployed,
+            } => !offline || !deployed.is_empty(),
+            Command::Analyze { deployed, .. } => !deployed.is_empty(),
+            Command::Search { gitea_links, .. } => *gitea_links,
+            Command::Export { offline, .. } | Command::Snapshot { offline } => !offline,
+            Command::Index | Command::Query { .. } | Command::Diff { .. } | Command::Exec { .. } => false,
+        }
+    }
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Analysis {
+    /// Parse App/config/applicationmetadata.json of every app
+    Metadata,
+
+    /// Report Altinn.App.* NuGet package versions and target frameworks from .csproj files
+    Packages,
+
+    /// Report the app-frontend-react version referenced from App/views/Home/Index.cshtml
+    Frontend,
+
+    /// Survey UI layout component usage across layout sets and pages under App/ui
+    Layouts {
+        /// Only report apps using this component type, e.g. `Input`
+        #[arg(long)]
+        component: Option<String>,
+
+        /// Only report components that have this property, e.g. `readOnly`
+        #[arg(long)]
+        property: Option<String>,
+    },
+
+    /// Summarize tasks, task types and gateways from App/config/process/process.bpmn
+    Process,
+
+    /// Report permissions and risky patterns from the XACML rules in App/config/authorization/policy.xml
+    Policy,
+
+    /// Report supported languages, missing translations and undefined text resource references
+    Texts,
+
+    /// Inventory of data models in App/models and the dataTypes they are linked to
+    Models,
+
+    /// Find app-lib interfaces implemented by custom C# code and their DI registrations
+    Code,
+
+    /// Report static, dynamic and secured option lists and undefined optionsId references
+    Options,
+
+    /// Inventory of Dockerfile base images, .NET versions, custom build steps and helm chart versions
+    Docker,
+
+    /// Run the pluggable analyzers in parallel across all apps and merge their findings into one report
+    Report {
+        /// Only run these analyzers, e.g. `--analyzer metadata --analyzer policy` (default: all)
+        #[arg(long = "analyzer")]
+        analyzers: Vec<String>,
+
+        /// Write the report as JSON to this file
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Only analyze apps in these orgs
+        #[arg(long = "org")]
+        orgs: Vec<String>,
+
+        /// Don't fetch repo and org details from Gitea and the CDN
+        #[arg(long, conflicts_with = "remote")]
+        offline: bool,
+
+        /// Analyze apps without cloning, by fetching the files the analyzers need from the default branch in Gitea
+        #[arg(long)]
+        remote: bool,
+    },
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryFile {
+    /// App/config/applicationmetadata.json
+    Metadata,
+    /// App/ui/layout-sets.json
+    LayoutSets,
+    /// Layout pages under App/ui
+    Layouts,
+    /// Text resources in App/config/texts
+    Texts,
 }
 
 pub struct Configuration {
     pub dir: PathBuf,
     pub base_url: Url,
+    pub tt02_url: Url,
+    pub production_url: Url,
     pub username: String,
     pub password: String,
+    pub command: Command,
 }
 
 impl Configuration {
@@ -55,27 +309,94 @@ impl Configuration {
 
         let base_url = Url::parse(&base_url).context("Failed to parse base url")?;
 
-        let username = args
-            .username
-            .or(settings.get::<String>("username").ok())
-            .ok_or_else(|| {
-                anyhow!("Username is required - must be configured either as an argument or in a config file")
-            })?;
+        let (default_tt02_url, default_production_url) = platform_urls(&base_url);
+        let tt02_url = args
+            .tt02_url
+            .or(settings.get::<String>("tt02_url").ok())
+            .unwrap_or(default_tt02_url);
+        let tt02_url = Url::parse(&tt02_url).context("Failed to parse tt02 url")?;
+        let production_url = args
+            .production_url
+            .or(settings.get::<String>("production_url").ok())
+            .unwrap_or(default_production_url);
+        let production_url = Url::parse(&production_url).context("Failed to parse production url")?;
 
-        let password = args
-            .password
-            .or(settings.get::<String>("password").ok())
-            .ok_or_else(|| {
-                anyhow!("Password is required - must be configured either as an argument or in a config file")
-            })?;
+        let command = args.command.unwrap_or(Command::Clone {
+            deployed: Vec::new(),
+            branch: None,
+        });
+
+        // Commands that only work on what has been cloned can run without credentials
+        let username = args.username.or(settings.get::<String>("username").ok());
+        let username = match username {
+            Some(username) => username,
+            None if !command.uses_gitea() => String::new(),
+            None => {
+                return Err(anyhow!(
+                    "Username is required - must be configured either as an argument or in a config file"
+                ))
+            }
+        };
+
+        let password = args.password.or(settings.get::<String>("password").ok());
+        let password = match password {
+            Some(password) => password,
+            None if !command.uses_gitea() => String::new(),
+            None => {
+                return Err(anyhow!(
+                    "Password is required - must be configured either as an argument or in a config file"
+                ))
+            }
+        };
 
         let config = Configuration {
             dir,
             base_url,
+            tt02_url,
+            production_url,
             username,
             password,
+            command,
         };
 
         Ok(Box::leak(Box::new(config)))
     }
 }
+
+/// The platform urls of tt02 and production for the Altinn instance at the base url. Altinn Studio deploys to the
+/// Altinn platform, while other instances, like a local one, are expected to serve the platform APIs themselves
+fn platform_urls(base_url: &Url) -> (String, String) {
+    match base_url.host_str() {
+        Some("altinn.studio") => (
+            "https://platform.tt02.altinn.no/".to_string(),
+            "https://platform.altinn.no/".to_string(),
+        ),
+        _ => (base_url.to_string(), base_url.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn derives_the_platform_urls_from_the_base_url() {
+        let studio = Url::parse("https://altinn.studio").unwrap();
+        assert_eq!(
+            platform_urls(&studio),
+            (
+                "https://platform.tt02.altinn.no/".to_string(),
+                "https://platform.altinn.no/".to_string()
+            )
+        );
+
+        let local = Url::parse("http://localhost:8000/").unwrap();
+        assert_eq!(
+            platform_urls(&local),
+            (
+                "http://localhost:8000/".to_string(),
+                "http://localhost:8000/".to_string()
+            )
+        );
+    }
+}
@@ -0,0 +1,231 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::LocalApp;
+use crate::configuration::Configuration;
+use crate::gitea_client::{GiteaClient, GiteaRelease, GiteaRepo};
+use crate::storage_client::{self, StorageClient};
+
+/// Where an app has been deployed, as registered in Altinn Storage.
+///
+/// This is not derived from Gitea releases and tags and the environments of the org in the CDN. Studio creates a
+/// release for every build, whether or not it's deployed, the environment only shows up in free text names, and
+/// the CDN environments are those the org may deploy to, not those each app is deployed to
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Deployment {
+    /// Never built
+    Neither,
+    /// Built in Studio, but not deployed to any environment
+    Built,
+    Tt02,
+    Production,
+    Both,
+}
+
+impl Deployment {
+    /// Whether the app matches a `--deployed` filter. `tt02` and `production` include apps deployed to both
+    pub fn matches(&self, filter: Deployment) -> bool {
+        match filter {
+            Deployment::Tt02 => matches!(self, Deployment::Tt02 | Deployment::Both),
+            Deployment::Production => matches!(self, Deployment::Production | Deployment::Both),
+            filter => *self == filter,
+        }
+    }
+
+    /// Whether the app matches any of the filters, or there are no filters
+    pub fn matches_any(&self, filters: &[Deployment]) -> bool {
+        filters.is_empty() || filters.iter().any(|f| self.matches(*f))
+    }
+}
+
+impl fmt::Display for Deployment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Deployment::Neither => "neither",
+            Deployment::Built => "built",
+            Deployment::Tt02 => "tt02",
+            Deployment::Production => "production",
+            Deployment::Both => "both",
+        })
+    }
+}
+
+/// Apps deployed to each environment, keyed by `org/name` in lowercase
+#[derive(Default, Debug, Clone)]
+pub struct Deployed {
+    pub tt02: HashSet<String>,
+    pub production: HashSet<String>,
+}
+
+impl Deployed {
+    /// Apps are registered in the Storage of an environment when they are deployed there
+    pub async fn fetch(config: &Configuration) -> Result<Self> {
+        let client = StorageClient::new();
+        let ids =
+            |apps: Vec<storage_client::StorageApplication>| apps.into_iter().map(|a| a.id.to_lowercase()).collect();
+
+        Ok(Deployed {
+            tt02: ids(client.get_applications(&config.tt02_url).await?),
+            production: ids(client.get_applications(&config.production_url).await?),
+        })
+    }
+}
+
+/// Classifies an app from where it's registered in Storage, and its releases for apps that aren't deployed.
+/// Studio creates a release for every build, so an app without releases has never been built
+pub fn classify(key: &str, releases: &[GiteaRelease], deployed: &Deployed) -> Deployment {
+    let key = key.to_lowercase();
+    match (deployed.tt02.contains(&key), deployed.production.contains(&key)) {
+        (true, true) => Deployment::Both,
+        (true, false) => Deployment::Tt02,
+        (false, true) => Deployment::Production,
+        (false, false) if releases.iter().any(|r| !r.draft) => Deployment::Built,
+        (false, false) => Deployment::Neither,
+    }
+}
+
+/// Fetches the releases of all repos concurrently, keyed by `org/name`
+pub async fn fetch_releases(client: &GiteaClient, repos: &[GiteaRepo]) -> Result<HashMap<String, Vec<GiteaRelease>>> {
+    client
+        .get_per_repo(repos, "releases", |client, repo| client.get_releases(repo))
+        .await
+}
+
+/// Keeps the repos whose deployment matches any of the filters. Repos that aren't deployed and whose releases
+/// couldn't be fetched are kept when filtering on `built` or `neither`, since it's unknown which of them they are
+pub async fn filter_repos(
+    config: &Configuration,
+    client: &GiteaClient,
+    repos: Vec<GiteaRepo>,
+    filters: &[Deployment],
+) -> Result<Vec<GiteaRepo>> {
+    if filters.is_empty() {
+        return Ok(repos);
+    }
+
+    let deployed = Deployed::fetch(config).await?;
+    let releases = fetch_releases(client, &repos).await?;
+    let undeployed_filter = filters
+        .iter()
+        .any(|f| matches!(f, Deployment::Built | Deployment::Neither));
+
+    let mut unclassified = Vec::new();
+    let repos = repos
+        .into_iter()
+        .filter(|repo| {
+            let Some((org, name)) = repo.org_and_name() else {
+                return false;
+            };
+            let key = format!("{}/{}", org, name);
+            if let Some(releases) = releases.get(&key) {
+                return classify(&key, releases, &deployed).matches_any(filters);
+            }
+
+            match classify(&key, &[], &deployed) {
+                Deployment::Neither if undeployed_filter => {
+                    unclassified.push(key);
+                    true
+                }
+                deployment => deployment.matches_any(filters),
+            }
+        })
+        .collect();
+
+    if !unclassified.is_empty() {
+        println!(
+            "Kept repos that may be built or not, since their releases couldn't be fetched ({}):",
+            unclassified.len()
+        );
+        for key in &unclassified {
+            println!("  {}", key);
+        }
+    }
+
+    Ok(repos)
+}
+
+/// Keeps the cloned apps whose deployment matches any of the filters
+pub async fn filter_apps(
+    config: &'static Configuration,
+    apps: Vec<LocalApp>,
+    filters: &[Deployment],
+) -> Result<Vec<LocalApp>> {
+    if filters.is_empty() {
+        return Ok(apps);
+    }
+
+    let repos = apps.iter().map(|a| a.gitea_repo(&config.base_url)).collect();
+    let keep = filter_repos(config, &GiteaClient::new(config), repos, filters)
+        .await?
+        .iter()
+        .filter_map(|r| r.org_and_name().map(|(org, name)| format!("{}/{}", org, name)))
+        .collect::<HashSet<_>>();
+
+    Ok(apps.into_iter().filter(|a| keep.contains(&a.key())).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn release(draft: bool) -> GiteaRelease {
+        GiteaRelease {
+            tag_name: "1".to_string(),
+            name: Some("Deploy to prod".to_string()),
+            draft,
+            ..Default::default()
+        }
+    }
+
+    fn deployed(tt02: &[&str], production: &[&str]) -> Deployed {
+        Deployed {
+            tt02: tt02.iter().map(|k| k.to_string()).collect(),
+            production: production.iter().map(|k| k.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn classifies_from_the_environments_apps_are_registered_in() {
+        let deployed = deployed(&["ttd/both", "ttd/test"], &["ttd/both", "ttd/prod"]);
+        let releases = [release(false)];
+
+        assert_eq!(classify("ttd/both", &releases, &deployed), Deployment::Both);
+        assert_eq!(classify("ttd/test", &releases, &deployed), Deployment::Tt02);
+        assert_eq!(classify("ttd/prod", &releases, &deployed), Deployment::Production);
+        assert_eq!(classify("TTD/Prod", &releases, &deployed), Deployment::Production);
+    }
+
+    #[test]
+    fn apps_with_releases_that_are_not_registered_are_only_built() {
+        let deployed = deployed(&[], &["ttd/other"]);
+
+        // Mentioning an environment in a release doesn't make the app deployed there
+        assert_eq!(classify("ttd/app", &[release(false)], &deployed), Deployment::Built);
+        assert_eq!(classify("ttd/app", &[release(true)], &deployed), Deployment::Neither);
+        assert_eq!(classify("ttd/app", &[], &deployed), Deployment::Neither);
+    }
+
+    #[test]
+    fn environment_filters_include_apps_deployed_to_both() {
+        assert!(Deployment::Both.matches(Deployment::Tt02));
+        assert!(Deployment::Both.matches(Deployment::Production));
+        assert!(Deployment::Tt02.matches(Deployment::Tt02));
+        assert!(!Deployment::Tt02.matches(Deployment::Production));
+        assert!(!Deployment::Built.matches(Deployment::Production));
+        assert!(!Deployment::Both.matches(Deployment::Built));
+        assert!(Deployment::Built.matches(Deployment::Built));
+        assert!(!Deployment::Built.matches(Deployment::Neither));
+    }
+
+    #[test]
+    fn no_filters_match_everything() {
+        assert!(Deployment::Neither.matches_any(&[]));
+        assert!(Deployment::Production.matches_any(&[Deployment::Tt02, Deployment::Production]));
+        assert!(!Deployment::Neither.matches_any(&[Deployment::Tt02, Deployment::Built]));
+    }
+}
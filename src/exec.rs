@@ -0,0 +1,272 @@
+use std::process::Command;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use futures::future::join_all;
+use spmc::Receiver;
+use tokio::task::{spawn_blocking, JoinError};
+
+use crate::analysis::{discover, LocalApp};
+use crate::configuration::Configuration;
+use crate::ui::Ui;
+
+const PROGRESS_MSG: &str = "Running command";
+
+/// Outcome of running the command in one repo
+pub struct ExecResult {
+    pub app: LocalApp,
+    /// `None` if the command couldn't be started or was killed by a signal
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    pub duration: Duration,
+    /// Why the command couldn't be started
+    pub error: Option<String>,
+}
+
+impl ExecResult {
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none() && self.exit_code == Some(0)
+    }
+}
+
+pub async fn run(
+    config: &'static Configuration,
+    command: &[String],
+    orgs: &[String],
+    repos: &[String],
+    jobs: Option<usize>,
+    verbose: bool,
+) -> Result<()> {
+    if jobs == Some(0) {
+        bail!("--jobs must be at least 1");
+    }
+
+    let dir = config.dir.clone();
+    let mut apps = spawn_blocking(move || discover(&dir))
+        .await
+        .context("Failed to wait for discovery")??;
+    apps.retain(|app| includes_app(app, orgs, repos));
+
+    let jobs = jobs.unwrap_or_else(num_cpus::get).min(apps.len().max(1));
+    let command = command_line(command);
+
+    println!("Running in: {}", config.dir.display());
+    println!("Command: {}", command);
+    println!("Number of repos: {}", apps.len());
+    println!("Jobs: {}", jobs);
+    println!("--------------------------------------------------");
+
+    let start = Instant::now();
+    let results = exec(apps, &command, jobs).await?;
+
+    print_results(&results, verbose);
+
+    let failed = results.iter().filter(|r| !r.succeeded()).collect::<Vec<_>>();
+    println!("--------------------------------------------------");
+    println!(
+        "Ran in {} repos in {:.1}s - succeeded={} failed={}",
+        results.len(),
+        start.elapsed().as_secs_f64(),
+        results.len() - failed.len(),
+        failed.len()
+    );
+    if failed.is_empty() {
+        return Ok(());
+    }
+
+    println!("Failed ({}):", failed.len());
+    for result in &failed {
+        println!("  {}: {}", result.app.key(), status(result));
+    }
+
+    bail!("The command failed in {} of {} repos", failed.len(), results.len())
+}
+
+/// The command line to pass to the shell. A single argument is used as is, so that it can use pipes, `&&` and
+/// the like, while multiple arguments are quoted so that each reaches the command unchanged
+pub fn command_line(args: &[String]) -> String {
+    match args {
+        [command] => command.clone(),
+        args => args.iter().map(|a| quote(a)).collect::<Vec<_>>().join(" "),
+    }
+}
+
+fn quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c));
+    if plain {
+        arg.to_string()
+    } else if cfg!(windows) {
+        format!("\"{}\"", arg.replace('"', "\\\""))
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// Orgs match on the org name, repos on either `name` or `org/name`
+fn includes_app(app: &LocalApp, orgs: &[String], repos: &[String]) -> bool {
+    (orgs.is_empty() || orgs.contains(&app.org))
+        && (repos.is_empty() || repos.iter().any(|r| *r == app.name || *r == app.key()))
+}
+
+/// Runs the command in all apps with at most `jobs` at a time, returning the results sorted by org and name
+pub async fn exec(apps: Vec<LocalApp>, command: &str, jobs: usize) -> Result<Vec<ExecResult>> {
+    let total = apps.len() as u64;
+    let command = Arc::new(command.to_string());
+    let done = Arc::new(AtomicU64::new(0));
+
+    let (mut tx, rx) = spmc::channel::<LocalApp>();
+
+    let (ui, ui_thread) = Ui::new();
+
+    let mut threads = Vec::with_capacity(jobs);
+    for _ in 0..jobs {
+        let rx = rx.clone();
+        let command = command.clone();
+        let done = done.clone();
+        let ui = ui.clone();
+
+        threads.push(spawn_blocking(move || thread(rx, &command, &done, total, &ui)));
+    }
+
+    for app in apps {
+        tx.send(app).context("Failed to queue app")?;
+    }
+
+    drop(tx);
+
+    let results = join_all(threads)
+        .await
+        .into_iter()
+        .collect::<Result<Vec<_>, JoinError>>()?;
+
+    drop(ui);
+
+    ui_thread.await.context("Failed to wait for UI thread")?;
+
+    let mut result = results.into_iter().flatten().collect::<Vec<_>>();
+    result.sort_by(|a, b| a.app.org.cmp(&b.app.org).then_with(|| a.app.name.cmp(&b.app.name)));
+
+    Ok(result)
+}
+
+fn thread(rx: Receiver<LocalApp>, command: &str, done: &AtomicU64, total: u64, ui: &Ui) -> Vec<ExecResult> {
+    let mut result = Vec::new();
+
+    while let Ok(app) = rx.recv() {
+        result.push(exec_app(app, command));
+
+        let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+        ui.update(PROGRESS_MSG, done, total);
+    }
+
+    result
+}
+
+/// Runs the command through the shell in the app dir, capturing its output
+pub fn exec_app(app: LocalApp, command: &str) -> ExecResult {
+    let mut process = if cfg!(windows) {
+        let mut process = Command::new("cmd");
+        process.arg("/C").arg(command);
+        process
+    } else {
+        let mut process = Command::new("sh");
+        process.arg("-c").arg(command);
+        process
+    };
+    process
+        .current_dir(&app.dir)
+        .env("ALTINN_ORG", &app.org)
+        .env("ALTINN_APP", &app.name);
+
+    let start = Instant::now();
+    let output = process.output();
+    let duration = start.elapsed();
+
+    match output {
+        Ok(output) => ExecResult {
+            app,
+            exit_code: output.status.code(),
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+            duration,
+            error: None,
+        },
+        Err(err) => ExecResult {
+            app,
+            exit_code: None,
+            stdout: String::new(),
+            stderr: String::new(),
+            duration,
+            error: Some(format!("Failed to start command: {}", err)),
+        },
+    }
+}
+
+fn status(result: &ExecResult) -> String {
+    match (&result.error, result.exit_code) {
+        (Some(err), _) => err.clone(),
+        (None, Some(code)) => format!("exit code {}", code),
+        (None, None) => "killed by signal".to_string(),
+    }
+}
+
+fn print_results(results: &[ExecResult], verbose: bool) {
+    for result in results.iter().filter(|r| verbose || !r.succeeded()) {
+        println!();
+        println!(
+            "{} ({}, {:.1}s)",
+            result.app.key(),
+            status(result),
+            result.duration.as_secs_f64()
+        );
+        print_output("stdout", &result.stdout);
+        print_output("stderr", &result.stderr);
+    }
+}
+
+fn print_output(name: &str, output: &str) {
+    let output = output.trim_end();
+    if output.is_empty() {
+        return;
+    }
+
+    println!("  {}:", name);
+    for line in output.lines() {
+        println!("    {}", line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn single_argument_is_a_shell_command_line() {
+        assert_eq!(
+            command_line(&args(&["dotnet build App && dotnet test"])),
+            "dotnet build App && dotnet test"
+        );
+    }
+
+    #[test]
+    #[cfg(not(windows))]
+    fn multiple_arguments_keep_their_quoting() {
+        assert_eq!(command_line(&args(&["dotnet", "build", "App"])), "dotnet build App");
+        assert_eq!(
+            command_line(&args(&["grep", "-r", "foo bar", "."])),
+            "grep -r 'foo bar' ."
+        );
+        assert_eq!(command_line(&args(&["echo", "it's", ""])), "echo 'it'\\''s' ''");
+        assert_eq!(command_line(&args(&["echo", "$HOME", "*"])), "echo '$HOME' '*'");
+    }
+}
@@ -0,0 +1,299 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::de::{MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+use super::{Table, TABLES};
+use crate::analysis::analyzer::{Finding, Severity};
+use crate::inventory::{CommitInfo, GitHistory, Inventory, Org, ReleaseInfo, Repo};
+
+/// Writes `orgs.csv`, `repos.csv` and `findings.csv` into the directory.
+/// Nested fields become dotted columns like `gitea.clone_url` and `cdn.name.en`
+pub fn write(dir: &Path, inventory: &Inventory) -> Result<()> {
+    for table in TABLES {
+        let path = dir.join(format!("{}.csv", table.name()));
+        match table {
+            Table::Orgs => write_table(&path, &org_template(), &inventory.orgs)?,
+            Table::Repos => write_table(&path, &repo_template(), &inventory.repos)?,
+            Table::Findings => write_table(&path, &finding_template(), &inventory.report.findings)?,
+        }
+    }
+
+    Ok(())
+}
+
+/// The columns are taken from a template row with every optional struct present,
+/// so the header doesn't depend on which fields happen to be set in the data
+fn write_table<T: Serialize>(path: &Path, template: &T, rows: &[T]) -> Result<()> {
+    let mut columns = Vec::new();
+    flatten("", &to_value(template)?, &mut |column, _| columns.push(column));
+
+    let mut writer = csv::Writer::from_path(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    writer.write_record(&columns).context("Failed to write CSV header")?;
+
+    for row in rows {
+        let mut values = HashMap::new();
+        flatten("", &to_value(row)?, &mut |column, value| {
+            values.insert(column, value);
+        });
+        writer
+            .write_record(
+                columns
+                    .iter()
+                    .map(|c| values.get(c).map(|v| v.as_str()).unwrap_or_default()),
+            )
+            .context("Failed to write CSV record")?;
+    }
+
+    writer
+        .flush()
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+
+    Ok(())
+}
+
+/// A serialized row where objects keep their fields in declaration order, so that columns follow the structs.
+/// `Value` sorts the fields of objects by name
+enum Node {
+    Object(Vec<(String, Node)>),
+    Leaf(Value),
+}
+
+impl<'de> Deserialize<'de> for Node {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct NodeVisitor;
+
+        impl<'de> Visitor<'de> for NodeVisitor {
+            type Value = Node;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("any JSON value")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Node, A::Error> {
+                let mut fields = Vec::new();
+                while let Some((name, node)) = map.next_entry::<String, Node>()? {
+                    fields.push((name, node));
+                }
+                Ok(Node::Object(fields))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Node, A::Error> {
+                let mut items = Vec::new();
+                while let Some(item) = seq.next_element::<Value>()? {
+                    items.push(item);
+                }
+                Ok(Node::Leaf(Value::Array(items)))
+            }
+
+            fn visit_bool<E>(self, v: bool) -> Result<Node, E> {
+                Ok(Node::Leaf(Value::Bool(v)))
+            }
+
+            fn visit_i64<E>(self, v: i64) -> Result<Node, E> {
+                Ok(Node::Leaf(Value::from(v)))
+            }
+
+            fn visit_u64<E>(self, v: u64) -> Result<Node, E> {
+                Ok(Node::Leaf(Value::from(v)))
+            }
+
+            fn visit_f64<E>(self, v: f64) -> Result<Node, E> {
+                Ok(Node::Leaf(Value::from(v)))
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Node, E> {
+                Ok(Node::Leaf(Value::from(v)))
+            }
+
+            fn visit_unit<E>(self) -> Result<Node, E> {
+                Ok(Node::Leaf(Value::Null))
+            }
+        }
+
+        deserializer.deserialize_any(NodeVisitor)
+    }
+}
+
+fn to_value(row: &impl Serialize) -> Result<Node> {
+    // Serializing to text keeps the field order, which is then read back into nodes
+    let json = serde_json::to_string(row).context("Failed to serialize CSV record")?;
+    serde_json::from_str(&json).context("Failed to serialize CSV record")
+}
+
+/// Calls `f` with the column name and cell value of every leaf, in field order.
+/// Lists of values are joined with `;` so they fit in a single cell
+fn flatten(prefix: &str, node: &Node, f: &mut dyn FnMut(String, String)) {
+    match node {
+        Node::Object(fields) => {
+            for (name, node) in fields {
+                let column = if prefix.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{}.{}", prefix, name)
+                };
+                flatten(&column, node, f);
+            }
+        }
+        Node::Leaf(Value::Array(items)) => f(prefix.to_string(), items.iter().map(cell).collect::<Vec<_>>().join(";")),
+        Node::Leaf(value) => f(prefix.to_string(), cell(value)),
+    }
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        value => value.to_string(),
+    }
+}
+
+fn org_template() -> Org {
+    Org {
+        gitea: Some(Default::default()),
+        cdn: Some(Default::default()),
+        ..Default::default()
+    }
+}
+
+fn repo_template() -> Repo {
+    Repo {
+        latest_commit: Some(CommitInfo::default()),
+        history: Some(GitHistory::default()),
+        latest_release: Some(ReleaseInfo::default()),
+        ..Default::default()
+    }
+}
+
+fn finding_template() -> Finding {
+    Finding {
+        org: String::new(),
+        app: String::new(),
+        analyzer: String::new(),
+        severity: Severity::Info,
+        kind: String::new(),
+        name: String::new(),
+        value: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use chrono::Utc;
+
+    use super::*;
+    use crate::analysis::analyzer::Report;
+    use crate::cdn_client::{CdnOrganization, Name};
+    use crate::gitea_client::GiteaOrganization;
+
+    fn read(path: &Path) -> (Vec<String>, Vec<Vec<String>>) {
+        let mut reader = csv::Reader::from_path(path).unwrap();
+        let header = reader.headers().unwrap().iter().map(|h| h.to_string()).collect();
+        let records = reader
+            .records()
+            .map(|r| r.unwrap().iter().map(|c| c.to_string()).collect())
+            .collect();
+        (header, records)
+    }
+
+    fn inventory() -> Inventory {
+        Inventory {
+            created: Utc::now(),
+            orgs: vec![
+                Org {
+                    name: "ttd".to_string(),
+                    gitea: Some(GiteaOrganization {
+                        id: 1,
+                        name: Some("ttd".to_string()),
+                        ..Default::default()
+                    }),
+                    cdn: Some(CdnOrganization {
+                        name: Name {
+                            en: "Test \"Department\"; Digdir".to_string(),
+                            nb: "Testdepartementet".to_string(),
+                            nn: "Testdepartementet,\nDigdir".to_string(),
+                        },
+                        environments: vec!["tt02".to_string(), "production".to_string()],
+                        ..Default::default()
+                    }),
+                },
+                Org {
+                    name: "personal".to_string(),
+                    ..Default::default()
+                },
+            ],
+            repos: Vec::new(),
+            report: Report {
+                created: Utc::now(),
+                analyzers: Vec::new(),
+                apps: Vec::new(),
+                findings: Vec::new(),
+            },
+        }
+    }
+
+    #[test]
+    fn writes_nested_fields_as_dotted_columns_in_field_order() {
+        let dir = tempfile::tempdir().unwrap();
+
+        write(dir.path(), &inventory()).unwrap();
+        let (header, records) = read(&dir.path().join("orgs.csv"));
+
+        let column = |name: &str| header.iter().position(|h| h == name).unwrap();
+        assert_eq!(header[0], "name");
+        assert!(column("gitea.id") < column("gitea.name"));
+        assert!(column("gitea.name") < column("cdn.name.en"));
+        assert_eq!(
+            [column("cdn.name.en"), column("cdn.name.nb"), column("cdn.name.nn")],
+            [
+                column("cdn.name.en"),
+                column("cdn.name.en") + 1,
+                column("cdn.name.en") + 2
+            ]
+        );
+        assert!(column("cdn.name.nn") < column("cdn.environments"));
+
+        // Orgs without Gitea or CDN details still have every column
+        assert_eq!(records.len(), 2);
+        assert!(records.iter().all(|r| r.len() == header.len()));
+        assert_eq!(records[1][column("name")], "personal");
+        assert_eq!(records[1][column("cdn.name.en")], "");
+    }
+
+    #[test]
+    fn joins_lists_and_escapes_separators_and_quotes() {
+        let dir = tempfile::tempdir().unwrap();
+
+        write(dir.path(), &inventory()).unwrap();
+        let content = fs::read_to_string(dir.path().join("orgs.csv")).unwrap();
+        let (header, records) = read(&dir.path().join("orgs.csv"));
+
+        let column = |name: &str| header.iter().position(|h| h == name).unwrap();
+        assert_eq!(records[0][column("gitea.id")], "1");
+        assert_eq!(records[0][column("cdn.environments")], "tt02;production");
+        assert_eq!(records[0][column("cdn.name.en")], "Test \"Department\"; Digdir");
+        assert_eq!(records[0][column("cdn.name.nn")], "Testdepartementet,\nDigdir");
+        assert!(content.contains(r#""Test ""Department""; Digdir""#));
+        assert!(content.contains("\"Testdepartementet,\nDigdir\""));
+    }
+
+    #[test]
+    fn writes_a_header_for_empty_tables() {
+        let dir = tempfile::tempdir().unwrap();
+
+        write(dir.path(), &inventory()).unwrap();
+        let (header, records) = read(&dir.path().join("findings.csv"));
+
+        assert_eq!(
+            header,
+            vec!["org", "app", "analyzer", "severity", "kind", "name", "value"]
+        );
+        assert!(records.is_empty());
+    }
+}
@@ -0,0 +1,381 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+
+use crate::analysis::analyzer::{Finding, Severity};
+use crate::inventory::{self, Inventory, Org, Repo};
+
+const STYLE: &str = "
+body { font-family: system-ui, sans-serif; margin: 2em; color: #1e2b3c; }
+h1, h2 { font-weight: 500; }
+table { border-collapse: collapse; margin-bottom: 2em; }
+th, td { text-align: left; padding: 0.3em 0.8em; border-bottom: 1px solid #dde3e8; vertical-align: top; }
+th { background: #f4f5f6; }
+table.sortable th { cursor: pointer; user-select: none; position: sticky; top: 0; }
+th[data-order=asc]::after { content: ' \\25B2'; }
+th[data-order=desc]::after { content: ' \\25BC'; }
+tr:hover td { background: #f9fafb; }
+.muted { color: #68707c; }
+.warning { color: #b25d00; }
+.error { color: #c22020; }
+";
+
+/// Sorts a table by the clicked column, numbers and versions in numeric order
+const SCRIPT: &str = "
+document.querySelectorAll('table.sortable th').forEach((th, i) => th.addEventListener('click', () => {
+  const table = th.closest('table');
+  const asc = th.dataset.order !== 'asc';
+  table.querySelectorAll('th').forEach(h => delete h.dataset.order);
+  th.dataset.order = asc ? 'asc' : 'desc';
+  const key = row => row.cells[i].dataset.sort ?? row.cells[i].textContent.trim();
+  const rows = [...table.tBodies[0].rows].sort((a, b) => key(a).localeCompare(key(b), undefined, { numeric: true }));
+  table.tBodies[0].append(...(asc ? rows : rows.reverse()));
+}));
+";
+
+/// What the report shows about an app, derived from the inventory and the findings of the analyzers
+struct AppSummary<'a> {
+    repo: &'a Repo,
+    app_lib: Option<&'a str>,
+    frontend: Option<&'a str>,
+    task_types: BTreeSet<&'a str>,
+    findings: Vec<&'a Finding>,
+}
+
+impl<'a> AppSummary<'a> {
+    fn new(repo: &'a Repo, findings: Vec<&'a Finding>) -> Self {
+        let app_lib = inventory::app_lib_version(&findings);
+        let frontend = inventory::frontend_version(&findings);
+        let task_types = inventory::task_types(&findings);
+
+        AppSummary {
+            repo,
+            app_lib,
+            frontend,
+            task_types,
+            findings,
+        }
+    }
+
+    fn count(&self, severity: Severity) -> usize {
+        self.findings.iter().filter(|f| f.severity == severity).count()
+    }
+
+    fn last_activity(&self) -> Option<DateTime<Utc>> {
+        self.repo.latest_commit.as_ref().map(|c| c.time)
+    }
+
+    fn page_path(&self) -> String {
+        format!("apps/{}/{}.html", self.repo.org, self.repo.name)
+    }
+}
+
+/// Writes `index.html` and a page per app into the directory. Every page is self-contained,
+/// with styles and scripts inlined, so the directory can be zipped and sent around as is
+pub fn write(dir: &Path, inventory: &Inventory) -> Result<()> {
+    let mut findings = inventory.findings_by_app();
+    let apps = inventory
+        .repos
+        .iter()
+        .map(|repo| AppSummary::new(repo, findings.remove(&repo.key()).unwrap_or_default()))
+        .collect::<Vec<_>>();
+
+    write_page(&dir.join("index.html"), &index(inventory, &apps))?;
+    for app in apps.iter().filter(|a| a.repo.cloned) {
+        write_page(&dir.join(app.page_path()), &app_page(inventory, app))?;
+    }
+
+    Ok(())
+}
+
+fn write_page(path: &Path, html: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    fs::write(path, html).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+fn index(inventory: &Inventory, apps: &[AppSummary]) -> String {
+    let mut body = String::new();
+    let cloned = apps.iter().filter(|a| a.repo.cloned).count();
+
+    let _ = write!(
+        body,
+        "<h1>Altinn apps</h1><p class=\"muted\">Generated {} - {} orgs, {} repos, {} cloned and analyzed</p>",
+        inventory.created.format("%Y-%m-%d %H:%M UTC"),
+        inventory.orgs.len(),
+        apps.len(),
+        cloned
+    );
+
+    body.push_str("<h2>Orgs</h2>");
+    let rows = inventory
+        .orgs
+        .iter()
+        .map(|org| {
+            let org_apps = apps.iter().filter(|a| a.repo.org == org.name).collect::<Vec<_>>();
+            let last_activity = org_apps.iter().filter_map(|a| a.last_activity()).max();
+            vec![
+                escape(&org.name),
+                escape(&display_name(org)),
+                escape(&org.cdn.as_ref().map(|c| c.environments.join(", ")).unwrap_or_default()),
+                org_apps.len().to_string(),
+                org_apps.iter().filter(|a| a.repo.cloned).count().to_string(),
+                date(last_activity),
+            ]
+        })
+        .collect();
+    table(
+        &mut body,
+        &["Org", "Name", "Environments", "Repos", "Cloned", "Last activity"],
+        rows,
+    );
+
+    body.push_str("<h2>Versions</h2>");
+    let analyzed = apps.iter().filter(|a| a.repo.cloned).collect::<Vec<_>>();
+    histogram(&mut body, "App-lib version", analyzed.iter().map(|a| a.app_lib));
+    histogram(&mut body, "Frontend version", analyzed.iter().map(|a| a.frontend));
+    histogram(
+        &mut body,
+        "Process task type",
+        analyzed.iter().flat_map(|a| a.task_types.iter().map(|t| Some(*t))),
+    );
+
+    body.push_str("<h2>Apps</h2>");
+    let rows = apps
+        .iter()
+        .map(|app| {
+            let name = if app.repo.cloned {
+                format!(
+                    "<a href=\"{}\">{}</a>",
+                    escape(&app.page_path()),
+                    escape(&app.repo.key())
+                )
+            } else {
+                format!("{} <span class=\"muted\">(not cloned)</span>", escape(&app.repo.key()))
+            };
+            vec![
+                name,
+                escape(app.app_lib.unwrap_or_default()),
+                escape(app.frontend.unwrap_or_default()),
+                escape(&app.task_types.iter().copied().collect::<Vec<_>>().join(", ")),
+                app.repo.deployment.map(|d| d.to_string()).unwrap_or_default(),
+                date(app.repo.latest_release.as_ref().and_then(|r| r.created)),
+                date(app.last_activity()),
+                count(app.repo.history.as_ref().map(|h| h.commits_last_90_days)),
+                count(app.repo.history.as_ref().map(|h| h.contributors)),
+                app.count(Severity::Warning).to_string(),
+                app.count(Severity::Error).to_string(),
+                gitea_link(app.repo),
+            ]
+        })
+        .collect();
+    table(
+        &mut body,
+        &[
+            "App",
+            "App-lib",
+            "Frontend",
+            "Task types",
+            "Deployed",
+            "Latest release",
+            "Last activity",
+            "Commits (90 days)",
+            "Contributors",
+            "Warnings",
+            "Errors",
+            "Gitea",
+        ],
+        rows,
+    );
+
+    page("Altinn apps", &body)
+}
+
+fn app_page(inventory: &Inventory, app: &AppSummary) -> String {
+    let mut body = String::new();
+    let org = inventory.org(&app.repo.org);
+
+    let _ = write!(
+        body,
+        "<p><a href=\"../../index.html\">All apps</a></p><h1>{}</h1><p>{} - {}</p>",
+        escape(&app.repo.key()),
+        escape(&org.map(display_name).unwrap_or_default()),
+        gitea_link(app.repo)
+    );
+
+    let mut details = vec![
+        vec!["App-lib".to_string(), escape(app.app_lib.unwrap_or("-"))],
+        vec!["Frontend".to_string(), escape(app.frontend.unwrap_or("-"))],
+        vec![
+            "Task types".to_string(),
+            escape(&app.task_types.iter().copied().collect::<Vec<_>>().join(", ")),
+        ],
+    ];
+    if let Some(deployment) = app.repo.deployment {
+        details.push(vec!["Deployed".to_string(), deployment.to_string()]);
+    }
+    if let Some(branches) = app.repo.branch_count {
+        details.push(vec![
+            "Branches".to_string(),
+            format!(
+                "{} <span class=\"muted\">(default {})</span>",
+                branches,
+                escape(app.repo.gitea.default_branch.as_deref().unwrap_or("-"))
+            ),
+        ]);
+    }
+    if let Some(release) = &app.repo.latest_release {
+        details.push(vec![
+            "Latest release".to_string(),
+            format!(
+                "{} {} <span class=\"muted\">at {}, {} releases in total</span>",
+                escape(release.name.as_deref().unwrap_or(&release.tag_name)),
+                date(release.created),
+                escape(release.target_commit.as_deref().unwrap_or("-")),
+                count(app.repo.release_count)
+            ),
+        ]);
+    }
+    if let Some(commit) = &app.repo.latest_commit {
+        details.push(vec!["Last activity".to_string(), date(Some(commit.time))]);
+        details.push(vec![
+            "Latest commit".to_string(),
+            format!(
+                "<a href=\"{}\">{}</a> {} <span class=\"muted\">by {}</span>",
+                escape(&commit_link(app.repo, &commit.id)),
+                escape(&commit.id[..commit.id.len().min(10)]),
+                escape(commit.summary.as_deref().unwrap_or_default()),
+                escape(&commit.author)
+            ),
+        ]);
+    }
+    if let Some(history) = &app.repo.history {
+        details.push(vec![
+            "Commits".to_string(),
+            format!(
+                "{} since {}, {} in the last 30 days and {} in the last 90 days",
+                history.commit_count,
+                date(Some(history.first_commit)),
+                history.commits_last_30_days,
+                history.commits_last_90_days
+            ),
+        ]);
+        details.push(vec![
+            "Contributors".to_string(),
+            format!(
+                "{}, most recently {}",
+                history.contributors,
+                escape(&history.last_author)
+            ),
+        ]);
+    }
+    body.push_str("<table>");
+    for row in details {
+        let _ = write!(body, "<tr><th>{}</th><td>{}</td></tr>", row[0], row[1]);
+    }
+    body.push_str("</table>");
+
+    body.push_str("<h2>Findings</h2>");
+    let rows = app
+        .findings
+        .iter()
+        .map(|f| {
+            vec![
+                escape(&f.analyzer),
+                format!("<span class=\"{}\">{}</span>", f.severity, f.severity),
+                escape(&f.kind),
+                escape(&f.name),
+                escape(f.value.as_deref().unwrap_or_default()),
+            ]
+        })
+        .collect();
+    table(&mut body, &["Analyzer", "Severity", "Kind", "Name", "Value"], rows);
+
+    page(&app.repo.key(), &body)
+}
+
+/// Number of apps per value, `-` for apps where it's unknown
+fn histogram<'a>(body: &mut String, title: &str, values: impl Iterator<Item = Option<&'a str>>) {
+    let mut counts = BTreeMap::<&str, usize>::new();
+    for value in values {
+        *counts.entry(value.unwrap_or("-")).or_default() += 1;
+    }
+
+    let rows = counts
+        .into_iter()
+        .map(|(value, count)| vec![escape(value), count.to_string()])
+        .collect();
+    table(body, &[title, "Apps"], rows);
+}
+
+/// Cells are expected to be escaped already, since some of them are links
+fn table(body: &mut String, headers: &[&str], rows: Vec<Vec<String>>) {
+    body.push_str("<table class=\"sortable\"><thead><tr>");
+    for header in headers {
+        let _ = write!(body, "<th>{}</th>", escape(header));
+    }
+    body.push_str("</tr></thead><tbody>");
+    for row in rows {
+        body.push_str("<tr>");
+        for cell in row {
+            let _ = write!(body, "<td>{}</td>", cell);
+        }
+        body.push_str("</tr>");
+    }
+    body.push_str("</tbody></table>");
+}
+
+fn page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\"><head><meta charset=\"utf-8\"><title>{}</title><style>{}</style></head>\
+         <body>{}<script>{}</script></body></html>\n",
+        escape(title),
+        STYLE,
+        body,
+        SCRIPT
+    )
+}
+
+fn display_name(org: &Org) -> String {
+    match &org.cdn {
+        Some(cdn) if !cdn.name.nb.is_empty() => cdn.name.nb.clone(),
+        Some(cdn) => cdn.name.en.clone(),
+        None => org.gitea.as_ref().and_then(|g| g.full_name.clone()).unwrap_or_default(),
+    }
+}
+
+fn gitea_link(repo: &Repo) -> String {
+    format!("<a href=\"{}\">Gitea</a>", escape(repo.gitea.web_url()))
+}
+
+fn commit_link(repo: &Repo, id: &str) -> String {
+    format!("{}/commit/{}", repo.gitea.web_url(), id)
+}
+
+fn count(count: Option<usize>) -> String {
+    count.map(|c| c.to_string()).unwrap_or_default()
+}
+
+fn date(time: Option<DateTime<Utc>>) -> String {
+    time.map(|t| t.format("%Y-%m-%d").to_string()).unwrap_or_default()
+}
+
+fn escape(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&#39;"),
+            c => result.push(c),
+        }
+    }
+    result
+}
@@ -0,0 +1,40 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use super::{Table, TABLES};
+use crate::inventory::Inventory;
+
+/// Writes `orgs.jsonl`, `repos.jsonl` and `findings.jsonl` into the directory, one JSON object per line
+/// with the same field names as the Gitea and CDN APIs and the analyzer report
+pub fn write(dir: &Path, inventory: &Inventory) -> Result<()> {
+    for table in TABLES {
+        let path = dir.join(format!("{}.jsonl", table.name()));
+        match table {
+            Table::Orgs => write_lines(&path, &inventory.orgs)?,
+            Table::Repos => write_lines(&path, &inventory.repos)?,
+            Table::Findings => write_lines(&path, &inventory.report.findings)?,
+        }
+    }
+
+    Ok(())
+}
+
+fn write_lines<T: Serialize>(path: &Path, rows: &[T]) -> Result<()> {
+    let file = File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut writer = BufWriter::new(file);
+
+    for row in rows {
+        serde_json::to_writer(&mut writer, row).context("Failed to serialize JSON line")?;
+        writer.write_all(b"\n")?;
+    }
+
+    writer
+        .flush()
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+
+    Ok(())
+}
@@ -0,0 +1,91 @@
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use tokio::task::spawn_blocking;
+
+use crate::configuration::Configuration;
+use crate::deployment::Deployment;
+use crate::{inventory, snapshot};
+
+pub mod csv;
+pub mod html;
+pub mod jsonl;
+pub mod sqlite;
+
+/// Files written by the CSV and JSON Lines exporters, one per table
+#[derive(Debug, Clone, Copy)]
+pub enum Table {
+    Orgs,
+    Repos,
+    Findings,
+}
+
+pub const TABLES: [Table; 3] = [Table::Orgs, Table::Repos, Table::Findings];
+
+impl Table {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Table::Orgs => "orgs",
+            Table::Repos => "repos",
+            Table::Findings => "findings",
+        }
+    }
+}
+
+pub async fn run(
+    config: &'static Configuration,
+    sqlite: Option<PathBuf>,
+    csv: Option<PathBuf>,
+    jsonl: Option<PathBuf>,
+    html: Option<PathBuf>,
+    offline: bool,
+    deployed: &[Deployment],
+) -> Result<()> {
+    if sqlite.is_none() && csv.is_none() && jsonl.is_none() && html.is_none() {
+        bail!("Nothing to export, specify an output with --sqlite, --csv, --jsonl or --html");
+    }
+
+    println!("Exporting: {}", config.dir.display());
+
+    let inventory = inventory::collect(config, offline, None).await?;
+
+    // The snapshot covers all apps, so that snapshots can be compared no matter what was exported
+    let (mut inventory, snapshot) =
+        spawn_blocking(move || snapshot::save(&config.dir, &inventory).map(|p| (inventory, p)))
+            .await
+            .context("Failed to wait for saving snapshot")??;
+    println!("Saved snapshot to {}", snapshot.display());
+
+    inventory.retain_deployed(deployed);
+
+    println!("Number of orgs: {}", inventory.orgs.len());
+    println!("Number of repos: {}", inventory.repos.len());
+    println!("Number of findings: {}", inventory.report.findings.len());
+    println!("--------------------------------------------------");
+
+    spawn_blocking(move || {
+        if let Some(path) = &sqlite {
+            sqlite::write(path, &inventory)?;
+            println!("Wrote SQLite database to {}", path.display());
+        }
+        if let Some(dir) = &csv {
+            fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+            csv::write(dir, &inventory)?;
+            println!("Wrote CSV files to {}", dir.display());
+        }
+        if let Some(dir) = &jsonl {
+            fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+            jsonl::write(dir, &inventory)?;
+            println!("Wrote JSON Lines files to {}", dir.display());
+        }
+        if let Some(dir) = &html {
+            html::write(dir, &inventory)?;
+            println!("Wrote HTML report to {}", dir.join("index.html").display());
+        }
+
+        Ok(())
+    })
+    .await
+    .context("Failed to wait for export")?
+}
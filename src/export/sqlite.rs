@@ -0,0 +1,255 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection};
+
+use crate::inventory::Inventory;
+
+/// Orgs, repos and findings are keyed by org name and `org/name` respectively,
+/// which is also how they're laid out on disk and identified in Gitea
+const SCHEMA: &str = "
+CREATE TABLE export (
+    created TEXT NOT NULL
+);
+
+CREATE TABLE orgs (
+    name TEXT PRIMARY KEY,
+    gitea_id INTEGER,
+    full_name TEXT,
+    description TEXT,
+    email TEXT,
+    location TEXT,
+    visibility TEXT,
+    avatar_url TEXT,
+    name_en TEXT,
+    name_nb TEXT,
+    name_nn TEXT,
+    orgnr TEXT,
+    homepage TEXT,
+    logo TEXT
+);
+
+CREATE TABLE org_environments (
+    org TEXT NOT NULL REFERENCES orgs(name),
+    environment TEXT NOT NULL,
+    PRIMARY KEY (org, environment)
+);
+
+CREATE TABLE repos (
+    id TEXT PRIMARY KEY,
+    org TEXT NOT NULL REFERENCES orgs(name),
+    name TEXT NOT NULL,
+    gitea_id INTEGER,
+    full_name TEXT,
+    clone_url TEXT NOT NULL,
+    ssh_url TEXT,
+    html_url TEXT,
+    default_branch TEXT,
+    private INTEGER,
+    cloned INTEGER NOT NULL,
+    deployment TEXT,
+    release_count INTEGER,
+    tag_count INTEGER,
+    branch_count INTEGER
+);
+
+CREATE TABLE commits (
+    repo TEXT PRIMARY KEY REFERENCES repos(id),
+    id TEXT NOT NULL,
+    time TEXT NOT NULL,
+    author TEXT NOT NULL,
+    email TEXT,
+    summary TEXT
+);
+
+CREATE TABLE latest_releases (
+    repo TEXT PRIMARY KEY REFERENCES repos(id),
+    tag_name TEXT NOT NULL,
+    name TEXT,
+    created TEXT,
+    target_commit TEXT
+);
+
+CREATE TABLE history (
+    repo TEXT PRIMARY KEY REFERENCES repos(id),
+    commit_count INTEGER NOT NULL,
+    first_commit TEXT NOT NULL,
+    last_commit TEXT NOT NULL,
+    contributors INTEGER NOT NULL,
+    commits_last_30_days INTEGER NOT NULL,
+    commits_last_90_days INTEGER NOT NULL,
+    last_author TEXT NOT NULL
+);
+
+CREATE TABLE findings (
+    repo TEXT NOT NULL REFERENCES repos(id),
+    analyzer TEXT NOT NULL,
+    severity TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    name TEXT NOT NULL,
+    value TEXT
+);
+
+CREATE INDEX findings_repo ON findings(repo);
+CREATE INDEX findings_kind ON findings(analyzer, kind, name);
+";
+
+/// Writes the inventory to a new SQLite database, replacing the file if it exists
+pub fn write(path: &Path, inventory: &Inventory) -> Result<()> {
+    match fs::remove_file(path) {
+        Ok(_) => {}
+        Err(e) if e.kind() == ErrorKind::NotFound => {}
+        Err(e) => return Err(e).with_context(|| format!("Failed to remove {}", path.display())),
+    }
+
+    let mut conn =
+        Connection::open(path).with_context(|| format!("Failed to create SQLite database {}", path.display()))?;
+    conn.execute_batch("PRAGMA foreign_keys = ON;")
+        .context("Failed to enable foreign keys")?;
+
+    let tx = conn.transaction().context("Failed to start transaction")?;
+    tx.execute_batch(SCHEMA).context("Failed to create tables")?;
+
+    tx.execute(
+        "INSERT INTO export (created) VALUES (?1)",
+        params![inventory.created.to_rfc3339()],
+    )
+    .context("Failed to insert export")?;
+
+    {
+        let mut insert_org = tx.prepare(
+            "INSERT INTO orgs (name, gitea_id, full_name, description, email, location, visibility, avatar_url,
+                name_en, name_nb, name_nn, orgnr, homepage, logo)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+        )?;
+        let mut insert_environment = tx.prepare("INSERT INTO org_environments (org, environment) VALUES (?1, ?2)")?;
+        for org in &inventory.orgs {
+            let gitea = org.gitea.as_ref();
+            let cdn = org.cdn.as_ref();
+            insert_org
+                .execute(params![
+                    org.name,
+                    gitea.map(|g| g.id),
+                    gitea.and_then(|g| g.full_name.as_deref()),
+                    gitea.and_then(|g| g.description.as_deref()),
+                    gitea.and_then(|g| g.email.as_deref()),
+                    gitea.and_then(|g| g.location.as_deref()),
+                    gitea.and_then(|g| g.visibility.as_deref()),
+                    gitea.and_then(|g| g.avatar_url.as_deref()),
+                    cdn.map(|c| c.name.en.as_str()),
+                    cdn.map(|c| c.name.nb.as_str()),
+                    cdn.map(|c| c.name.nn.as_str()),
+                    cdn.map(|c| c.orgnr.as_str()),
+                    cdn.map(|c| c.homepage.as_str()),
+                    cdn.and_then(|c| c.logo.as_deref()),
+                ])
+                .with_context(|| format!("Failed to insert org {}", org.name))?;
+
+            for environment in cdn.iter().flat_map(|c| c.environments.iter()) {
+                insert_environment
+                    .execute(params![org.name, environment])
+                    .with_context(|| format!("Failed to insert environment {} for {}", environment, org.name))?;
+            }
+        }
+
+        let mut insert_repo = tx.prepare(
+            "INSERT INTO repos (id, org, name, gitea_id, full_name, clone_url, ssh_url, html_url, default_branch,
+                private, cloned, deployment, release_count, tag_count, branch_count)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+        )?;
+        let mut insert_commit =
+            tx.prepare("INSERT INTO commits (repo, id, time, author, email, summary) VALUES (?1, ?2, ?3, ?4, ?5, ?6)")?;
+        let mut insert_release = tx.prepare(
+            "INSERT INTO latest_releases (repo, tag_name, name, created, target_commit) VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        let mut insert_history = tx.prepare(
+            "INSERT INTO history (repo, commit_count, first_commit, last_commit, contributors, commits_last_30_days,
+                commits_last_90_days, last_author)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        )?;
+        for repo in &inventory.repos {
+            let key = repo.key();
+            // Repos synthesized from what's on disk don't have a Gitea id
+            let gitea_id = Some(repo.gitea.id).filter(|id| *id != 0);
+            insert_repo
+                .execute(params![
+                    key,
+                    repo.org,
+                    repo.name,
+                    gitea_id,
+                    repo.gitea.full_name,
+                    repo.gitea.clone_url,
+                    repo.gitea.ssh_url,
+                    repo.gitea.html_url,
+                    repo.gitea.default_branch,
+                    repo.gitea.private,
+                    repo.cloned,
+                    repo.deployment.map(|d| d.to_string()),
+                    repo.release_count,
+                    repo.tag_count,
+                    repo.branch_count,
+                ])
+                .with_context(|| format!("Failed to insert repo {}", key))?;
+
+            if let Some(commit) = &repo.latest_commit {
+                insert_commit
+                    .execute(params![
+                        key,
+                        commit.id,
+                        commit.time.to_rfc3339(),
+                        commit.author,
+                        commit.email,
+                        commit.summary,
+                    ])
+                    .with_context(|| format!("Failed to insert commit for {}", key))?;
+            }
+            if let Some(release) = &repo.latest_release {
+                insert_release
+                    .execute(params![
+                        key,
+                        release.tag_name,
+                        release.name,
+                        release.created.map(|c| c.to_rfc3339()),
+                        release.target_commit,
+                    ])
+                    .with_context(|| format!("Failed to insert latest release for {}", key))?;
+            }
+            if let Some(history) = &repo.history {
+                insert_history
+                    .execute(params![
+                        key,
+                        history.commit_count,
+                        history.first_commit.to_rfc3339(),
+                        history.last_commit.to_rfc3339(),
+                        history.contributors,
+                        history.commits_last_30_days,
+                        history.commits_last_90_days,
+                        history.last_author,
+                    ])
+                    .with_context(|| format!("Failed to insert history for {}", key))?;
+            }
+        }
+
+        let mut insert_finding = tx.prepare(
+            "INSERT INTO findings (repo, analyzer, severity, kind, name, value) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        )?;
+        for finding in &inventory.report.findings {
+            insert_finding
+                .execute(params![
+                    finding.key(),
+                    finding.analyzer,
+                    finding.severity.to_string(),
+                    finding.kind,
+                    finding.name,
+                    finding.value,
+                ])
+                .with_context(|| format!("Failed to insert finding for {}", finding.key()))?;
+        }
+    }
+
+    tx.commit().context("Failed to commit transaction")?;
+
+    Ok(())
+}
@@ -26,7 +26,8 @@ impl GitClient {
         Ok(())
     }
 
-    pub fn clone(url: &str, config: &Configuration, ui: &Ui) -> Result<()> {
+    /// Clones the repo into `<dir>/<org>/<name>`, checking out the branch if given, otherwise the remote HEAD
+    pub fn clone(url: &str, branch: Option<&str>, config: &Configuration, ui: &Ui) -> Result<()> {
         Self::assert_invariants().context("Failed to clone")?;
 
         let (org, repo_name) = match url.rsplitn(3, '/').collect::<Vec<_>>()[..] {
@@ -38,10 +39,10 @@ impl GitClient {
 
         let repo_dir = config.dir.join(org).join(repo_name);
 
-        Self::clone_core(url, &repo_dir, ui, config)
+        Self::clone_core(url, branch, &repo_dir, ui, config)
     }
 
-    fn clone_core(url: &str, repo_dir: &PathBuf, ui: &Ui, config: &Configuration) -> Result<()> {
+    fn clone_core(url: &str, branch: Option<&str>, repo_dir: &PathBuf, ui: &Ui, config: &Configuration) -> Result<()> {
         let state = RefCell::new(State {
             indexed_objects: 0,
             total_objects: 0,
@@ -83,7 +84,11 @@ impl GitClient {
 
         let mut fo = FetchOptions::new();
         fo.remote_callbacks(cb);
-        RepoBuilder::new()
+        let mut builder = RepoBuilder::new();
+        if let Some(branch) = branch {
+            builder.branch(branch);
+        }
+        builder
             .fetch_options(fo)
             .with_checkout(co)
             .clone(url, Path::new(repo_dir))
@@ -1,11 +1,20 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+
 use anyhow::anyhow;
 use anyhow::Result;
 use anyhow::{bail, Context};
-use reqwest::{header, Client, ClientBuilder};
+use chrono::{DateTime, Utc};
+use futures::{stream, StreamExt};
+use reqwest::{header, Client, ClientBuilder, StatusCode, Url};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 
 use crate::configuration::Configuration;
+use crate::ui::Ui;
 
+#[derive(Clone)]
 pub struct GiteaClient {
     client: Client,
     configuration: &'static Configuration,
@@ -132,6 +141,258 @@ impl GiteaClient {
 
         Ok(result)
     }
+
+    /// Paths of all files in the repo on the default branch
+    pub async fn get_files(&self, repo: &GiteaRepo) -> Result<Vec<String>> {
+        let (org, name) = repo.org_and_name().context("Invalid git url")?;
+        let branch = repo.default_branch.as_deref().unwrap_or("master");
+
+        let mut result = Vec::<String>::new();
+
+        let mut page = 1;
+        const PAGE_SIZE: usize = 1000;
+
+        loop {
+            let mut url = self
+                .configuration
+                .base_url
+                .join(&format!("/repos/api/v1/repos/{}/{}/git/trees/{}", org, name, branch))
+                .context("Failed to build URL")?;
+
+            url.query_pairs_mut()
+                .append_pair("recursive", "true")
+                .append_pair("page", &page.to_string())
+                .append_pair("per_page", &PAGE_SIZE.to_string());
+
+            let response = self.client.get(url).send().await;
+
+            let response = response.context("Failed to fetch tree - send request")?;
+
+            let status = response.status();
+            let body = response
+                .text()
+                .await
+                .context("Failed to fetch tree - reading body of request")?;
+
+            if !status.is_success() {
+                bail!(
+                    "Failed to fetch tree - invalid status - status={} content={}",
+                    status,
+                    body
+                );
+            }
+
+            let response = serde_json::from_str::<GiteaTree>(&body)
+                .map_err(|err| anyhow!("Failed to parse tree: {:?}\nBody={}", err, body))?;
+
+            result.extend(
+                response
+                    .tree
+                    .into_iter()
+                    .filter(|e| e.entry_type == "blob")
+                    .map(|e| e.path),
+            );
+
+            if !response.truncated {
+                break;
+            }
+
+            page += 1;
+        }
+
+        result.sort();
+
+        Ok(result)
+    }
+
+    /// Content of a file on the default branch, `None` if it doesn't exist
+    pub async fn get_file(&self, repo: &GiteaRepo, path: &str) -> Result<Option<String>> {
+        let (org, name) = repo.org_and_name().context("Invalid git url")?;
+        let branch = repo.default_branch.as_deref().unwrap_or("master");
+
+        let mut url = self
+            .configuration
+            .base_url
+            .join("/repos/api/v1/repos")
+            .context("Failed to build URL")?;
+        // Paths can contain characters like `#`, `?` and spaces, so each segment is encoded
+        url.path_segments_mut()
+            .map_err(|_| anyhow!("Failed to build URL - base url can't have a path"))?
+            .extend([org, name, "raw"])
+            .extend(path.split('/'));
+        url.query_pairs_mut().append_pair("ref", branch);
+
+        let response = self.client.get(url).send().await;
+
+        let response = response.context("Failed to fetch file - send request")?;
+
+        let status = response.status();
+        if status == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        let body = response
+            .text()
+            .await
+            .context("Failed to fetch file - reading body of request")?;
+
+        if !status.is_success() {
+            bail!(
+                "Failed to fetch file - invalid status - status={} content={}",
+                status,
+                body
+            );
+        }
+
+        Ok(Some(body))
+    }
+
+    /// All releases of the repo, newest first. Studio creates a release for every build of an app
+    pub async fn get_releases(&self, repo: &GiteaRepo) -> Result<Vec<GiteaRelease>> {
+        self.get_paged(repo, "releases").await
+    }
+
+    /// All tags of the repo, newest first
+    pub async fn get_tags(&self, repo: &GiteaRepo) -> Result<Vec<GiteaTag>> {
+        self.get_paged(repo, "tags").await
+    }
+
+    /// All branches of the repo
+    pub async fn get_branches(&self, repo: &GiteaRepo) -> Result<Vec<GiteaBranch>> {
+        self.get_paged(repo, "branches").await
+    }
+
+    /// All pages of a listing under the repo, such as `releases`
+    async fn get_paged<T: DeserializeOwned>(&self, repo: &GiteaRepo, resource: &str) -> Result<Vec<T>> {
+        let (org, name) = repo.org_and_name().context("Invalid git url")?;
+        let mut result = Vec::<T>::new();
+
+        let mut page = 1;
+        const PAGE_SIZE: usize = 50;
+
+        loop {
+            let mut url = self
+                .configuration
+                .base_url
+                .join(&format!("/repos/api/v1/repos/{}/{}/{}", org, name, resource))
+                .context("Failed to build URL")?;
+
+            url.query_pairs_mut()
+                .append_pair("page", &page.to_string())
+                .append_pair("limit", &PAGE_SIZE.to_string());
+
+            let response = self.client.get(url).send().await;
+
+            let response = response.with_context(|| format!("Failed to fetch {} - send request", resource))?;
+
+            let status = response.status();
+            let body = response
+                .text()
+                .await
+                .with_context(|| format!("Failed to fetch {} - reading body of request", resource))?;
+
+            if !status.is_success() {
+                bail!(
+                    "Failed to fetch {} - invalid status - status={} content={}",
+                    resource,
+                    status,
+                    body
+                );
+            }
+
+            let response = serde_json::from_str::<Vec<T>>(&body)
+                .map_err(|err| anyhow!("Failed to parse {}: {:?}\nBody={}", resource, err, body))?;
+
+            let len = response.len();
+            result.extend(response);
+
+            if len < PAGE_SIZE {
+                break;
+            }
+
+            page += 1;
+        }
+
+        Ok(result)
+    }
+
+    /// Runs a request for every repo concurrently with progress, returning the results keyed by `org/name`.
+    /// Repos where the request failed are left out and reported
+    pub async fn get_per_repo<'a, T, F, Fut>(
+        &'a self,
+        repos: &'a [GiteaRepo],
+        what: &str,
+        f: F,
+    ) -> Result<HashMap<String, T>>
+    where
+        F: Fn(&'a GiteaClient, &'a GiteaRepo) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        const CONCURRENCY: usize = 16;
+
+        let total = repos.len() as u64;
+        if total == 0 {
+            return Ok(HashMap::new());
+        }
+        let message = format!("Fetching {} from Gitea", what);
+        let done = AtomicU64::new(0);
+        let (ui, ui_thread) = Ui::new();
+
+        let results = stream::iter(repos)
+            .map(|repo| {
+                let (ui, done, message, request) = (&ui, &done, &message, f(self, repo));
+                async move {
+                    let result = request.await;
+                    let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+                    ui.update(message, done, total);
+                    (repo, result)
+                }
+            })
+            .buffer_unordered(CONCURRENCY)
+            .collect::<Vec<_>>()
+            .await;
+
+        drop(ui);
+
+        ui_thread.await.context("Failed to wait for UI thread")?;
+        println!();
+
+        let mut result = HashMap::with_capacity(results.len());
+        let mut failed = Vec::new();
+        for (repo, value) in results {
+            let Some((org, name)) = repo.org_and_name() else {
+                continue;
+            };
+            match value {
+                Ok(value) => {
+                    result.insert(format!("{}/{}", org, name), value);
+                }
+                Err(err) => failed.push((format!("{}/{}", org, name), err)),
+            }
+        }
+
+        if !failed.is_empty() {
+            println!("Failed to fetch {} ({}):", what, failed.len());
+            for (key, err) in &failed {
+                println!("  {}: {:#}", key, err);
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Repos of all the given orgs
+    pub async fn get_all_repos(&self, orgs: &[GiteaOrganization]) -> Result<Vec<GiteaRepo>> {
+        let mut result = Vec::with_capacity(32);
+
+        for org in orgs.iter() {
+            let name = org.name.as_deref().context("All orgs should have a name")?;
+            let org_repos = self.get_repos(name).await?;
+            result.extend(org_repos);
+        }
+
+        Ok(result)
+    }
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -175,6 +436,9 @@ pub struct GiteaRepo {
     #[serde(rename = "url")]
     pub url: Option<String>,
 
+    #[serde(rename = "html_url")]
+    pub html_url: Option<String>,
+
     #[serde(rename = "name")]
     pub name: Option<String>,
 
@@ -190,3 +454,279 @@ pub struct GiteaRepo {
     #[serde(rename = "private")]
     pub private: Option<bool>,
 }
+
+/// Contents of App/config/applicationmetadata.json in an app repo
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApplicationMetadata {
+    pub id: String,
+    pub org: String,
+    #[serde(default)]
+    pub title: HashMap<String, String>,
+    #[serde(default)]
+    pub data_types: Vec<DataType>,
+    #[serde(default)]
+    pub party_types_allowed: PartyTypesAllowed,
+    #[serde(default)]
+    pub auto_delete_on_process_end: bool,
+    pub on_entry: Option<OnEntry>,
+    pub presentation_fields: Option<Vec<DataField>>,
+    pub data_fields: Option<Vec<DataField>>,
+    pub disallow_user_instantiation: Option<bool>,
+    pub altinn_nuget_version: Option<String>,
+    pub created: Option<String>,
+    pub created_by: Option<String>,
+    pub last_changed: Option<String>,
+    pub last_changed_by: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DataType {
+    pub id: String,
+    pub description: Option<HashMap<String, String>>,
+    pub allowed_content_types: Option<Vec<String>>,
+    pub allowed_contributers: Option<Vec<String>>,
+    pub app_logic: Option<AppLogic>,
+    pub task_id: Option<String>,
+    pub max_size: Option<i64>,
+    #[serde(default)]
+    pub max_count: i64,
+    #[serde(default)]
+    pub min_count: i64,
+    pub grouping: Option<String>,
+    pub enable_pdf_creation: Option<bool>,
+    pub enable_file_scan: Option<bool>,
+    pub validation_error_on_pending_file_scan: Option<bool>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppLogic {
+    pub auto_create: Option<bool>,
+    pub class_ref: Option<String>,
+    pub schema_ref: Option<String>,
+    pub allow_anonymous_on_stateless: Option<bool>,
+    pub auto_delete_on_process_end: Option<bool>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PartyTypesAllowed {
+    #[serde(default)]
+    pub bankruptcy_estate: bool,
+    #[serde(default)]
+    pub organisation: bool,
+    #[serde(default)]
+    pub person: bool,
+    #[serde(default)]
+    pub sub_unit: bool,
+}
+
+impl PartyTypesAllowed {
+    /// Names of the allowed party types. Empty means that all party types are allowed
+    pub fn allowed(&self) -> Vec<&'static str> {
+        [
+            ("bankruptcyEstate", self.bankruptcy_estate),
+            ("organisation", self.organisation),
+            ("person", self.person),
+            ("subUnit", self.sub_unit),
+        ]
+        .into_iter()
+        .filter_map(|(name, allowed)| allowed.then_some(name))
+        .collect()
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OnEntry {
+    pub show: Option<String>,
+    pub instance_selection: Option<InstanceSelection>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstanceSelection {
+    pub rows_per_page_options: Option<Vec<i64>>,
+    pub default_rows_per_page: Option<i64>,
+    pub default_selected_option: Option<i64>,
+    pub sort_direction: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DataField {
+    pub id: String,
+    pub path: Option<String>,
+    pub data_type_id: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GiteaRelease {
+    #[serde(rename = "id")]
+    pub id: i64,
+
+    #[serde(rename = "tag_name")]
+    pub tag_name: String,
+
+    /// Branch or commit the release was created from
+    #[serde(rename = "target_commitish")]
+    pub target_commitish: Option<String>,
+
+    #[serde(rename = "name")]
+    pub name: Option<String>,
+
+    #[serde(rename = "body")]
+    pub body: Option<String>,
+
+    #[serde(rename = "draft")]
+    pub draft: bool,
+
+    #[serde(rename = "prerelease")]
+    pub prerelease: bool,
+
+    #[serde(rename = "created_at")]
+    pub created_at: Option<DateTime<Utc>>,
+
+    #[serde(rename = "published_at")]
+    pub published_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GiteaBranch {
+    #[serde(rename = "name")]
+    pub name: String,
+
+    #[serde(rename = "commit")]
+    pub commit: Option<GiteaBranchCommit>,
+
+    #[serde(rename = "protected")]
+    pub protected: Option<bool>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GiteaBranchCommit {
+    #[serde(rename = "id")]
+    pub id: String,
+
+    #[serde(rename = "timestamp")]
+    pub timestamp: Option<DateTime<Utc>>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GiteaTag {
+    #[serde(rename = "name")]
+    pub name: String,
+
+    #[serde(rename = "id")]
+    pub id: Option<String>,
+
+    #[serde(rename = "message")]
+    pub message: Option<String>,
+
+    #[serde(rename = "commit")]
+    pub commit: Option<GiteaCommitMeta>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GiteaCommitMeta {
+    #[serde(rename = "sha")]
+    pub sha: String,
+
+    #[serde(rename = "created")]
+    pub created: Option<DateTime<Utc>>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GiteaTree {
+    #[serde(rename = "sha")]
+    pub sha: String,
+
+    #[serde(rename = "tree", default)]
+    pub tree: Vec<GiteaTreeEntry>,
+
+    #[serde(rename = "truncated")]
+    pub truncated: bool,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GiteaTreeEntry {
+    #[serde(rename = "path")]
+    pub path: String,
+
+    /// `blob` for files and `tree` for directories
+    #[serde(rename = "type")]
+    pub entry_type: String,
+
+    #[serde(rename = "size")]
+    pub size: Option<i64>,
+}
+
+impl GiteaRepo {
+    /// Org and repo name as derived from the clone url, which is also how repos are laid out on disk
+    pub fn org_and_name(&self) -> Option<(&str, &str)> {
+        let (rest, repo_name) = self.clone_url.rsplit_once('/')?;
+        let (_, org) = rest.rsplit_once('/')?;
+        let (repo_name, _) = repo_name.rsplit_once('.')?;
+        Some((org, repo_name))
+    }
+
+    /// The repo in the Gitea web UI
+    pub fn web_url(&self) -> &str {
+        // The web UI is served from the clone url without the .git suffix
+        self.html_url
+            .as_deref()
+            .unwrap_or_else(|| self.clone_url.trim_end_matches(".git"))
+    }
+
+    /// Link to a line of a file in the Gitea web UI at the given branch, tag or commit,
+    /// with the segments of the reference and path percent-encoded. Falls back to the repo if its url is invalid
+    pub fn web_link(&self, reference: &str, path: &str, line: Option<usize>) -> String {
+        let kind = if reference.len() == 40 && reference.chars().all(|c| c.is_ascii_hexdigit()) {
+            "commit"
+        } else {
+            "branch"
+        };
+        let fragment = line.map(|line| format!("L{}", line));
+
+        let Ok(mut url) = Url::parse(self.web_url()) else {
+            return self.web_url().to_string();
+        };
+        if let Ok(mut segments) = url.path_segments_mut() {
+            segments
+                .pop_if_empty()
+                .extend(["src", kind])
+                .extend(reference.split('/'))
+                .extend(path.split('/'));
+        }
+        url.set_fragment(fragment.as_deref());
+        url.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn web_links_encode_paths() {
+        let repo = GiteaRepo {
+            html_url: Some("https://altinn.studio/repos/ttd/app".to_string()),
+            ..Default::default()
+        };
+        let commit = "0123456789abcdef0123456789abcdef01234567";
+
+        assert_eq!(
+            repo.web_link(commit, "App/logic/My File#2.cs", Some(12)),
+            format!(
+                "https://altinn.studio/repos/ttd/app/src/commit/{}/App/logic/My%20File%232.cs#L12",
+                commit
+            )
+        );
+        assert_eq!(
+            repo.web_link("feature/v8", "App/App.csproj", None),
+            "https://altinn.studio/repos/ttd/app/src/branch/feature/v8/App/App.csproj"
+        );
+    }
+}
@@ -0,0 +1,477 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use git2::{Repository, Sort};
+use serde::{Deserialize, Serialize};
+use tokio::task::spawn_blocking;
+
+use crate::analysis::analyzer::{self, AppHandle, Finding, Report};
+use crate::analysis::runner::{self, Upstream};
+use crate::analysis::{discover, LocalApp};
+use crate::cdn_client::CdnOrganization;
+use crate::configuration::Configuration;
+use crate::deployment::{self, Deployed, Deployment};
+use crate::gitea_client::{GiteaClient, GiteaOrganization, GiteaRelease, GiteaRepo, GiteaTag};
+
+/// Everything known about the app landscape at one point in time, combining Gitea, the CDN,
+/// the cloned repos and the findings of the analyzers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Inventory {
+    pub created: DateTime<Utc>,
+    pub orgs: Vec<Org>,
+    pub repos: Vec<Repo>,
+    pub report: Report,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Org {
+    pub name: String,
+    /// `None` when collected offline
+    pub gitea: Option<GiteaOrganization>,
+    /// `None` for orgs that aren't registered in the CDN, like test and personal orgs
+    pub cdn: Option<CdnOrganization>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Repo {
+    pub org: String,
+    pub name: String,
+    pub gitea: GiteaRepo,
+    /// Whether the repo has been cloned into the directory
+    pub cloned: bool,
+    pub latest_commit: Option<CommitInfo>,
+    pub history: Option<GitHistory>,
+    /// This and the release, tag and branch stats are `None` when collected offline or from an analysis report,
+    /// for repos that haven't been cloned, or if they couldn't be fetched from Gitea
+    pub deployment: Option<Deployment>,
+    pub release_count: Option<usize>,
+    pub tag_count: Option<usize>,
+    pub branch_count: Option<usize>,
+    pub latest_release: Option<ReleaseInfo>,
+}
+
+/// The newest published release of a repo, which Studio creates on every build
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReleaseInfo {
+    pub tag_name: String,
+    pub name: Option<String>,
+    pub created: Option<DateTime<Utc>>,
+    /// Commit the release tag points to, or the branch it was created from if the tag wasn't found
+    pub target_commit: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommitInfo {
+    pub id: String,
+    pub time: DateTime<Utc>,
+    pub author: String,
+    pub email: Option<String>,
+    pub summary: Option<String>,
+}
+
+/// Statistics over all commits reachable from HEAD, telling actively maintained apps from abandoned ones.
+/// Commits are dated by when they were authored, so rebased or cherry-picked work isn't counted as recent
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GitHistory {
+    pub commit_count: usize,
+    pub first_commit: DateTime<Utc>,
+    pub last_commit: DateTime<Utc>,
+    /// Distinct commit authors, by email
+    pub contributors: usize,
+    pub commits_last_30_days: usize,
+    pub commits_last_90_days: usize,
+    /// Author of the most recently authored commit, identified the same way as in `contributors`
+    #[serde(alias = "last_committer")]
+    pub last_author: String,
+}
+
+impl Repo {
+    pub fn key(&self) -> String {
+        format!("{}/{}", self.org, self.name)
+    }
+}
+
+/// Packages whose version is the app-lib version of an app, in order of preference
+const APP_LIB_PACKAGES: [&str; 2] = ["Altinn.App.Api", "Altinn.App.Core"];
+
+impl Inventory {
+    pub fn org(&self, name: &str) -> Option<&Org> {
+        self.orgs.iter().find(|o| o.name == name)
+    }
+
+    /// Keeps the repos whose deployment matches any of the filters, and their findings
+    pub fn retain_deployed(&mut self, filters: &[Deployment]) {
+        if filters.is_empty() {
+            return;
+        }
+
+        self.repos
+            .retain(|r| r.deployment.is_some_and(|d| d.matches_any(filters)));
+        let keys = self
+            .repos
+            .iter()
+            .map(|r| format!("{}/{}", r.org, r.name))
+            .collect::<HashSet<_>>();
+        self.report.apps.retain(|a| keys.contains(a));
+        self.report.findings.retain(|f| keys.contains(&f.key()));
+    }
+
+    /// Findings grouped by app key, `org/name`
+    pub fn findings_by_app(&self) -> HashMap<String, Vec<&Finding>> {
+        let mut result = HashMap::<String, Vec<&Finding>>::new();
+        for finding in &self.report.findings {
+            result.entry(finding.key()).or_default().push(finding);
+        }
+        result
+    }
+}
+
+/// The app-lib version from the package references found by the packages analyzer
+pub fn app_lib_version<'a>(findings: &[&'a Finding]) -> Option<&'a str> {
+    APP_LIB_PACKAGES.iter().find_map(|package| {
+        findings
+            .iter()
+            .find(|f| f.analyzer == "packages" && f.kind == "package" && f.name == *package)
+            .and_then(|f| f.value.as_deref())
+    })
+}
+
+/// The frontend version found by the frontend analyzer, e.g. `4` or `4.5.2`
+pub fn frontend_version<'a>(findings: &[&'a Finding]) -> Option<&'a str> {
+    findings
+        .iter()
+        .find(|f| f.analyzer == "frontend" && f.kind == "version")
+        .map(|f| f.name.as_str())
+}
+
+/// Distinct types of the process tasks found by the process analyzer
+pub fn task_types<'a>(findings: &[&'a Finding]) -> BTreeSet<&'a str> {
+    findings
+        .iter()
+        .filter(|f| f.analyzer == "process" && f.kind == "task")
+        .filter_map(|f| f.value.as_deref())
+        .collect()
+}
+
+/// Distinct component types used in the layouts found by the layouts analyzer
+pub fn component_types<'a>(findings: &[&'a Finding]) -> BTreeSet<&'a str> {
+    findings
+        .iter()
+        .filter(|f| f.analyzer == "layouts" && f.kind == "component")
+        .map(|f| f.name.as_str())
+        .collect()
+}
+
+/// Collects the inventory, running all builtin analyzers on the cloned repos unless a report of them is given.
+/// When offline, orgs and repos are only derived from what has been cloned
+pub async fn collect(config: &'static Configuration, offline: bool, report: Option<Report>) -> Result<Inventory> {
+    let upstream = Upstream::fetch(config, offline).await?;
+    assemble(config, upstream, !offline, report).await
+}
+
+/// Collects the inventory around a report of all cloned repos, reusing the orgs and repos fetched for it.
+/// The releases, tags, branches and deployments of the repos are left out, since they take several requests per repo
+pub async fn from_report(config: &'static Configuration, upstream: Upstream, report: Report) -> Result<Inventory> {
+    assemble(config, upstream, false, Some(report)).await
+}
+
+async fn assemble(
+    config: &'static Configuration,
+    upstream: Upstream,
+    repo_details: bool,
+    report: Option<Report>,
+) -> Result<Inventory> {
+    let dir = config.dir.clone();
+    let apps = spawn_blocking(move || discover(&dir))
+        .await
+        .context("Failed to wait for discovery")??;
+
+    let Upstream {
+        gitea_orgs,
+        repos: gitea_repos,
+        mut cdn_orgs,
+    } = upstream;
+
+    let (deployed, mut releases, mut tags, mut branches) = if !repo_details {
+        (Deployed::default(), HashMap::new(), HashMap::new(), HashMap::new())
+    } else {
+        let gitea_client = GiteaClient::new(config);
+        // Releases, tags and branches are only fetched for the cloned repos, since every repo takes a request each
+        let cloned = apps.iter().map(|a| a.key()).collect::<HashSet<_>>();
+        let repos = gitea_repos
+            .iter()
+            .filter(|(key, _)| cloned.contains(*key))
+            .map(|(_, repo)| repo.clone())
+            .collect::<Vec<_>>();
+        let releases = deployment::fetch_releases(&gitea_client, &repos).await?;
+        let tags = gitea_client
+            .get_per_repo(&repos, "tags", |client, repo| client.get_tags(repo))
+            .await?;
+        let branches = gitea_client
+            .get_per_repo(&repos, "branches", |client, repo| client.get_branches(repo))
+            .await?;
+        (Deployed::fetch(config).await?, releases, tags, branches)
+    };
+
+    let mut orgs = BTreeMap::<String, Org>::new();
+    for org in gitea_orgs {
+        let Some(name) = org.name.clone() else {
+            continue;
+        };
+        orgs.insert(
+            name.clone(),
+            Org {
+                cdn: cdn_orgs.remove(&name),
+                gitea: Some(org),
+                name,
+            },
+        );
+    }
+
+    let mut repos = BTreeMap::<String, Repo>::new();
+    for (key, repo) in gitea_repos {
+        let Some((org, name)) = repo.org_and_name() else {
+            continue;
+        };
+        let releases = releases.remove(&key);
+        let tags = tags.remove(&key);
+        let branch_count = branches.remove(&key).map(|b| b.len());
+        let deployment = releases.as_ref().map(|r| deployment::classify(&key, r, &deployed));
+        let latest_release = releases
+            .as_ref()
+            .and_then(|r| latest_release(r, tags.as_deref().unwrap_or_default()));
+        let (org, name) = (org.to_string(), name.to_string());
+        repos.insert(
+            key,
+            Repo {
+                org,
+                name,
+                gitea: repo,
+                deployment,
+                release_count: releases.map(|r| r.len()),
+                tag_count: tags.map(|t| t.len()),
+                branch_count,
+                latest_release,
+                ..Default::default()
+            },
+        );
+    }
+
+    // Repos that have been deleted from Gitea since they were cloned are still part of the inventory
+    for app in &apps {
+        let repo = repos.entry(app.key()).or_insert_with(|| Repo {
+            org: app.org.clone(),
+            name: app.name.clone(),
+            gitea: app.gitea_repo(&config.base_url),
+            ..Default::default()
+        });
+        repo.cloned = true;
+    }
+    for repo in repos.values() {
+        orgs.entry(repo.org.clone()).or_insert_with(|| Org {
+            name: repo.org.clone(),
+            cdn: cdn_orgs.remove(&repo.org),
+            gitea: None,
+        });
+    }
+    // Orgs in the CDN that don't have any apps
+    for (name, cdn) in cdn_orgs {
+        orgs.insert(
+            name.clone(),
+            Org {
+                name,
+                gitea: None,
+                cdn: Some(cdn),
+            },
+        );
+    }
+
+    let created = Utc::now();
+    let commit_apps = apps.clone();
+    let commits = spawn_blocking(move || {
+        commit_apps
+            .iter()
+            .map(|app| (app.key(), (latest_commit(&app.dir), history(&app.dir, created))))
+            .collect::<HashMap<_, _>>()
+    })
+    .await
+    .context("Failed to wait for reading commits")?;
+    for (key, (commit, history)) in commits {
+        if let Some(repo) = repos.get_mut(&key) {
+            repo.latest_commit = commit;
+            repo.history = history;
+        }
+    }
+
+    let report = match report {
+        Some(report) => report,
+        None => analyze(apps, &orgs, &repos).await?,
+    };
+
+    Ok(Inventory {
+        created,
+        orgs: orgs.into_values().collect(),
+        repos: repos.into_values().collect(),
+        report,
+    })
+}
+
+/// Runs all builtin analyzers on the cloned repos
+async fn analyze(apps: Vec<LocalApp>, orgs: &BTreeMap<String, Org>, repos: &BTreeMap<String, Repo>) -> Result<Report> {
+    let handles = apps
+        .into_iter()
+        .filter_map(|app| {
+            let repo = repos.get(&app.key())?;
+            Some(AppHandle {
+                repo: repo.gitea.clone(),
+                org: orgs.get(&app.org).and_then(|o| o.cdn.clone()),
+                files: Box::new(app.dir.clone()),
+                app,
+                git: None,
+            })
+        })
+        .collect::<Vec<_>>();
+    runner::analyze(handles, analyzer::builtin()).await
+}
+
+/// The newest release that isn't a draft, resolving its target commit from the tags
+fn latest_release(releases: &[GiteaRelease], tags: &[GiteaTag]) -> Option<ReleaseInfo> {
+    let release = releases
+        .iter()
+        .filter(|r| !r.draft)
+        .max_by_key(|r| r.published_at.or(r.created_at))?;
+    let tag_commit = tags
+        .iter()
+        .find(|t| t.name == release.tag_name)
+        .and_then(|t| t.commit.as_ref())
+        .map(|c| c.sha.clone());
+
+    Some(ReleaseInfo {
+        tag_name: release.tag_name.clone(),
+        name: release.name.clone(),
+        created: release.published_at.or(release.created_at),
+        target_commit: tag_commit.or_else(|| release.target_commitish.clone()),
+    })
+}
+
+/// Walks all commits reachable from HEAD, `None` if the app dir isn't a git repo or has no commits
+pub fn history(app_dir: &Path, now: DateTime<Utc>) -> Option<GitHistory> {
+    let repo = Repository::open(app_dir).ok()?;
+    let head = repo.head().ok()?.peel_to_commit().ok()?;
+
+    let mut walk = repo.revwalk().ok()?;
+    walk.set_sorting(Sort::TIME).ok()?;
+    walk.push(head.id()).ok()?;
+
+    let mut result = GitHistory {
+        first_commit: DateTime::<Utc>::MAX_UTC,
+        last_commit: DateTime::<Utc>::MIN_UTC,
+        ..Default::default()
+    };
+    let mut contributors = BTreeSet::new();
+    for id in walk {
+        let Ok(commit) = id.and_then(|id| repo.find_commit(id)) else {
+            continue;
+        };
+        let author = commit.author();
+        let Some(time) = Utc.timestamp_opt(author.when().seconds(), 0).single() else {
+            continue;
+        };
+        let contributor = author.email().or(author.name()).unwrap_or_default().to_lowercase();
+
+        result.commit_count += 1;
+        result.first_commit = result.first_commit.min(time);
+        if time > result.last_commit {
+            result.last_commit = time;
+            result.last_author = contributor.clone();
+        }
+        if now - time <= Duration::days(30) {
+            result.commits_last_30_days += 1;
+        }
+        if now - time <= Duration::days(90) {
+            result.commits_last_90_days += 1;
+        }
+
+        contributors.insert(contributor);
+    }
+    result.contributors = contributors.len();
+
+    (result.commit_count > 0).then_some(result)
+}
+
+/// The commit HEAD points to, `None` if the app dir isn't a git repo or has no commits
+pub fn latest_commit(app_dir: &Path) -> Option<CommitInfo> {
+    let repo = Repository::open(app_dir).ok()?;
+    let commit = repo.head().ok()?.peel_to_commit().ok()?;
+    let author = commit.author();
+
+    Some(CommitInfo {
+        id: commit.id().to_string(),
+        time: Utc.timestamp_opt(commit.time().seconds(), 0).single()?,
+        author: author.name().unwrap_or_default().to_string(),
+        email: author.email().map(|e| e.to_string()),
+        summary: commit.summary().map(|s| s.to_string()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use git2::{Signature, Time};
+
+    use super::*;
+
+    /// Commits an empty tree on top of HEAD, authored and committed by the given people days before `now`
+    fn commit(repo: &Repository, now: DateTime<Utc>, author: (&str, i64), committer: (&str, i64)) {
+        let signature = |(email, days_ago): (&str, i64)| {
+            let time = Time::new((now - Duration::days(days_ago)).timestamp(), 0);
+            Signature::new("Test", email, &time).unwrap()
+        };
+        let tree = repo
+            .find_tree(repo.treebuilder(None).unwrap().write().unwrap())
+            .unwrap();
+        let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let parents = parent.iter().collect::<Vec<_>>();
+        repo.commit(
+            Some("HEAD"),
+            &signature(author),
+            &signature(committer),
+            "Commit",
+            &tree,
+            &parents,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn summarizes_the_commits_by_when_and_by_whom_they_were_authored() {
+        let now = Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        commit(&repo, now, ("a@example.com", 200), ("a@example.com", 200));
+        commit(&repo, now, ("B@example.com", 60), ("b@example.com", 60));
+        commit(&repo, now, ("b@example.com", 10), ("b@example.com", 10));
+        // Authored long ago, but rebased onto the branch today by someone else
+        commit(&repo, now, ("c@example.com", 100), ("a@example.com", 0));
+
+        let history = history(dir.path(), now).unwrap();
+
+        assert_eq!(history.commit_count, 4);
+        assert_eq!(history.contributors, 3);
+        assert_eq!(history.commits_last_30_days, 1);
+        assert_eq!(history.commits_last_90_days, 2);
+        assert_eq!(history.first_commit, now - Duration::days(200));
+        assert_eq!(history.last_commit, now - Duration::days(10));
+        assert_eq!(history.last_author, "b@example.com");
+    }
+
+    #[test]
+    fn has_no_history_without_commits() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(history(dir.path(), Utc::now()), None);
+
+        Repository::init(dir.path()).unwrap();
+        assert_eq!(history(dir.path(), Utc::now()), None);
+    }
+}
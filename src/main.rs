@@ -1,13 +1,16 @@
 #![allow(dead_code)]
 #![allow(unused_variables)]
 #![allow(unreachable_code)]
+#![allow(clippy::redundant_static_lifetimes)]
+#![allow(clippy::print_with_newline)]
 
 use std::io::ErrorKind;
 
 use anyhow::anyhow;
 use anyhow::{Context, Result};
 use cdn_client::CdnClient;
-use configuration::Configuration;
+use configuration::{Command, Configuration};
+use deployment::Deployment;
 use futures::future::join_all;
 use spmc::Receiver;
 use tokio::fs;
@@ -19,16 +22,78 @@ use crate::gitea_client::GiteaClient;
 use crate::gitea_client::GiteaRepo;
 use crate::ui::Ui;
 
+mod analysis;
 mod cdn_client;
 mod configuration;
+mod deployment;
+mod exec;
+mod export;
 mod git_client;
 mod gitea_client;
+mod inventory;
+mod query;
+mod search;
+mod snapshot;
+mod storage_client;
 mod ui;
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let config = Configuration::new()?;
 
+    match &config.command {
+        Command::Clone { deployed, branch } => clone(config, deployed, branch.as_deref()).await,
+        Command::Analyze { analysis, deployed } => analysis::run(config, analysis, deployed).await,
+        Command::Search {
+            pattern,
+            ignore_case,
+            orgs,
+            globs,
+            path,
+            no_index,
+            gitea_links,
+        } => {
+            let query = search::Query::new(pattern, *ignore_case, orgs, globs, path.as_deref())?;
+            search::run(config, query, *no_index, *gitea_links).await
+        }
+        Command::Index => search::update_index(config).await.map(|_| ()),
+        Command::Query {
+            expression,
+            files,
+            orgs,
+        } => query::run(config, expression, files, orgs).await,
+        Command::Export {
+            sqlite,
+            csv,
+            jsonl,
+            html,
+            offline,
+            deployed,
+        } => {
+            export::run(
+                config,
+                sqlite.clone(),
+                csv.clone(),
+                jsonl.clone(),
+                html.clone(),
+                *offline,
+                deployed,
+            )
+            .await
+        }
+        Command::Snapshot { offline } => snapshot::run(config, *offline).await,
+        Command::Diff { from, to } => snapshot::run_diff(config, from.as_deref(), to.as_deref()).await,
+        Command::Exec {
+            command,
+            orgs,
+            repos,
+            jobs,
+            verbose,
+        } => exec::run(config, command, orgs, repos, *jobs, *verbose).await,
+    }
+}
+
+async fn clone(config: &'static Configuration, deployed: &[Deployment], branch: Option<&str>) -> Result<()> {
     init(config).await?;
 
     println!("Cloning into: {}", config.dir.display());
@@ -39,13 +104,13 @@ async fn main() -> Result<()> {
     // let orgs = cdn_client.get_orgs().await?;
     let orgs = gitea_client.get_orgs().await?;
 
-    let mut repos = Vec::with_capacity(32);
-
-    for org in orgs.iter() {
-        let name = org.name.clone().context("All orgs should have a name")?;
-        let org_repos = gitea_client.get_repos(&name).await?;
-        repos.extend(org_repos);
-    }
+    let repos = gitea_client.get_all_repos(&orgs).await?;
+    let repos = if deployed.is_empty() {
+        repos
+    } else {
+        deployment::filter_repos(config, &gitea_client, repos, deployed).await?
+    };
+    let repos = checkout_branches(&gitea_client, repos, branch).await?;
 
     println!("Number of orgs: {}", orgs.len());
     println!("Number of repos: {}", repos.len());
@@ -53,7 +118,7 @@ async fn main() -> Result<()> {
 
     let cpus_to_use = num_cpus::get().min(4);
 
-    let (mut tx, rx) = spmc::channel::<GiteaRepo>();
+    let (mut tx, rx) = spmc::channel::<(GiteaRepo, Option<String>)>();
 
     let (ui, ui_thread) = Ui::new();
 
@@ -88,9 +153,54 @@ async fn main() -> Result<()> {
     println!("--------------------------------------------------");
     println!("Cloned {} repos", repo_count);
 
+    search::update_index(config).await?;
+
     Ok(())
 }
 
+/// Pairs the repos with the branch to check out, which is the given branch where it exists
+/// and the default branch otherwise. Without a branch, the remote HEAD is checked out
+async fn checkout_branches(
+    gitea_client: &GiteaClient,
+    repos: Vec<GiteaRepo>,
+    branch: Option<&str>,
+) -> Result<Vec<(GiteaRepo, Option<String>)>> {
+    let Some(branch) = branch else {
+        return Ok(repos.into_iter().map(|r| (r, None)).collect());
+    };
+
+    let branches = gitea_client
+        .get_per_repo(&repos, "branches", |client, repo| client.get_branches(repo))
+        .await?;
+
+    let mut found = 0;
+    let repos = repos
+        .into_iter()
+        .map(|repo| {
+            let exists = repo
+                .org_and_name()
+                .and_then(|(org, name)| branches.get(&format!("{}/{}", org, name)))
+                .is_some_and(|b| b.iter().any(|b| b.name == branch));
+            found += usize::from(exists);
+            let checkout = if exists {
+                Some(branch.to_string())
+            } else {
+                repo.default_branch.clone()
+            };
+            (repo, checkout)
+        })
+        .collect::<Vec<_>>();
+
+    println!(
+        "Repos with branch {}: {} (the other {} use their default branch)",
+        branch,
+        found,
+        repos.len() - found
+    );
+
+    Ok(repos)
+}
+
 async fn init(config: &Configuration) -> Result<()> {
     if is_root::is_root() {
         return Err(anyhow!("Can't run as root, it's safest to run as a normal user"));
@@ -134,11 +244,11 @@ async fn init(config: &Configuration) -> Result<()> {
     Ok(())
 }
 
-fn thread(id: usize, rx: Receiver<GiteaRepo>, config: &Configuration, ui: Ui) -> u64 {
+fn thread(id: usize, rx: Receiver<(GiteaRepo, Option<String>)>, config: &Configuration, ui: Ui) -> u64 {
     let mut count = 0;
 
-    while let Ok(repo) = rx.recv() {
-        match GitClient::clone(&repo.clone_url, config, &ui) {
+    while let Ok((repo, branch)) = rx.recv() {
+        match GitClient::clone(&repo.clone_url, branch.as_deref(), config, &ui) {
             Ok(_) => {}
             Err(err) => {
                 panic!("Thread {} - Failed to clone {}: {}", id, repo.clone_url, err);
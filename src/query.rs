@@ -0,0 +1,260 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+use serde_json_path::JsonPath;
+use tokio::task::spawn_blocking;
+
+use crate::analysis::layouts::{LAYOUT_SETS_PATH, UI_DIR};
+use crate::analysis::texts::TEXTS_DIR;
+use crate::analysis::{discover, find_files, list_files, metadata, read_file};
+use crate::configuration::{Configuration, QueryFile};
+
+const MAX_VALUE_LEN: usize = 200;
+
+pub struct QueryMatch {
+    pub path: String,
+    /// Normalized path of the match within the file, e.g. `$['dataTypes'][0]`
+    pub location: String,
+    pub value: Value,
+}
+
+pub async fn run(config: &'static Configuration, expression: &str, files: &[QueryFile], orgs: &[String]) -> Result<()> {
+    let path = JsonPath::parse(expression).context("Invalid JSONPath expression")?;
+    let files = if files.is_empty() {
+        vec![
+            QueryFile::Metadata,
+            QueryFile::LayoutSets,
+            QueryFile::Layouts,
+            QueryFile::Texts,
+        ]
+    } else {
+        files.to_vec()
+    };
+    let orgs = orgs.to_vec();
+
+    spawn_blocking(move || {
+        let mut apps = discover(&config.dir)?;
+        apps.retain(|app| orgs.is_empty() || orgs.contains(&app.org));
+
+        println!("Querying: {}", config.dir.display());
+        println!("Number of repos: {}", apps.len());
+        println!("--------------------------------------------------");
+
+        let mut matched_files = 0;
+        let mut matched_apps = 0;
+        let mut matches = 0;
+        let mut failed = Vec::new();
+
+        for app in &apps {
+            let (app_matches, errors) = match query_app(&app.dir, &path, &files) {
+                Ok(result) => result,
+                Err(err) => {
+                    failed.push((app, format!("{:#}", err)));
+                    continue;
+                }
+            };
+            failed.extend(errors.into_iter().map(|e| (app, e)));
+            if app_matches.is_empty() {
+                continue;
+            }
+
+            println!("{} ({} matches)", app.key(), app_matches.len());
+            let mut previous = None;
+            for m in &app_matches {
+                if previous != Some(&m.path) {
+                    println!("  {}", m.path);
+                    matched_files += 1;
+                }
+                previous = Some(&m.path);
+                println!("    {}: {}", m.location, truncate(&m.value.to_string(), MAX_VALUE_LEN));
+            }
+
+            matched_apps += 1;
+            matches += app_matches.len();
+        }
+
+        println!("--------------------------------------------------");
+        println!(
+            "{} matches in {} files across {} apps",
+            matches, matched_files, matched_apps
+        );
+        println!("Failed to parse ({}):", failed.len());
+        for (app, err) in &failed {
+            println!("  {}: {}", app.key(), err);
+        }
+
+        Ok(())
+    })
+    .await
+    .context("Failed to wait for query")?
+}
+
+/// Evaluates the JSONPath against the selected files of an app.
+/// Returns the matches and errors for files that couldn't be parsed
+pub fn query_app(app_dir: &Path, path: &JsonPath, files: &[QueryFile]) -> Result<(Vec<QueryMatch>, Vec<String>)> {
+    let mut result = Vec::new();
+    let mut errors = Vec::new();
+
+    for file in selected_files(app_dir, files)? {
+        let Some(content) = read_file(app_dir, &file)? else {
+            continue;
+        };
+        let value = match serde_json::from_str::<Value>(&content) {
+            Ok(value) => value,
+            Err(e) => {
+                errors.push(format!("{}: {}", file, e));
+                continue;
+            }
+        };
+
+        for node in path.query_located(&value) {
+            result.push(QueryMatch {
+                path: file.clone(),
+                location: node.location().to_string(),
+                value: node.node().clone(),
+            });
+        }
+    }
+
+    Ok((result, errors))
+}
+
+fn selected_files(app_dir: &Path, files: &[QueryFile]) -> Result<Vec<String>> {
+    let mut result = Vec::new();
+
+    for file in files {
+        match file {
+            QueryFile::Metadata => result.push(metadata::PATH.to_string()),
+            QueryFile::LayoutSets => result.push(LAYOUT_SETS_PATH.to_string()),
+            QueryFile::Layouts => result.extend(find_files(app_dir, |p| {
+                p.starts_with(UI_DIR)
+                    && (p.contains("/layouts/") || p.ends_with("/FormLayout.json"))
+                    && p.ends_with(".json")
+            })?),
+            QueryFile::Texts => result.extend(
+                list_files(app_dir, TEXTS_DIR)?
+                    .into_iter()
+                    .filter(|p| p.ends_with(".json")),
+            ),
+        }
+    }
+
+    Ok(result)
+}
+
+fn truncate(s: &str, max: usize) -> String {
+    match s.char_indices().nth(max) {
+        Some((i, _)) => format!("{}...", &s[..i]),
+        None => s.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn app_dir() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for (path, content) in [
+            (
+                metadata::PATH,
+                r#"{ "id": "ttd/app", "dataTypes": [{ "id": "model", "maxCount": 1 }, { "id": "attachment", "maxCount": 5 }] }"#,
+            ),
+            (
+                LAYOUT_SETS_PATH,
+                r#"{ "sets": [{ "id": "form", "dataType": "model" }] }"#,
+            ),
+            ("App/ui/form/Settings.json", r#"{ "pages": { "order": ["Page1"] } }"#),
+            (
+                "App/ui/form/layouts/Page1.json",
+                r#"{ "data": { "layout": [{ "id": "group", "type": "Group", "maxCount": 3 }, { "id": "name", "type": "Input" }] } }"#,
+            ),
+            (
+                "App/ui/FormLayout.json",
+                r#"{ "data": { "layout": [{ "id": "old", "type": "Input" }] } }"#,
+            ),
+            (
+                "App/config/texts/resource.nb.json",
+                r#"{ "language": "nb", "resources": [] }"#,
+            ),
+            ("App/config/texts/resource.en.json", "{ not json"),
+        ] {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        dir
+    }
+
+    fn query(dir: &Path, expression: &str, files: &[QueryFile]) -> (Vec<(String, String)>, Vec<String>) {
+        let path = JsonPath::parse(expression).unwrap();
+        let (matches, errors) = query_app(dir, &path, files).unwrap();
+        let matches = matches.into_iter().map(|m| (m.path, m.location)).collect();
+        (matches, errors)
+    }
+
+    #[test]
+    fn selects_the_files_of_each_kind() {
+        let dir = app_dir();
+
+        let mut layouts = selected_files(dir.path(), &[QueryFile::Layouts]).unwrap();
+        layouts.sort();
+        let mut texts = selected_files(dir.path(), &[QueryFile::Texts]).unwrap();
+        texts.sort();
+
+        assert_eq!(
+            selected_files(dir.path(), &[QueryFile::Metadata, QueryFile::LayoutSets]).unwrap(),
+            vec![metadata::PATH, LAYOUT_SETS_PATH]
+        );
+        // Settings.json is not a layout
+        assert_eq!(
+            layouts,
+            vec!["App/ui/FormLayout.json", "App/ui/form/layouts/Page1.json"]
+        );
+        assert_eq!(
+            texts,
+            vec!["App/config/texts/resource.en.json", "App/config/texts/resource.nb.json"]
+        );
+    }
+
+    #[test]
+    fn evaluates_the_expression_against_the_selected_files() {
+        let dir = app_dir();
+
+        assert_eq!(
+            query(dir.path(), "$.dataTypes[?@.maxCount > 1].id", &[QueryFile::Metadata]),
+            (
+                vec![(metadata::PATH.to_string(), "$['dataTypes'][1]['id']".to_string())],
+                Vec::new()
+            )
+        );
+        assert_eq!(
+            query(
+                dir.path(),
+                "$.data.layout[?@.type == 'Group' && @.maxCount]",
+                &[QueryFile::Layouts]
+            )
+            .0,
+            vec![(
+                "App/ui/form/layouts/Page1.json".to_string(),
+                "$['data']['layout'][0]".to_string()
+            )]
+        );
+        // Only the selected files are queried
+        assert!(query(dir.path(), "$.sets", &[QueryFile::Metadata]).0.is_empty());
+    }
+
+    #[test]
+    fn reports_files_that_are_not_json() {
+        let dir = app_dir();
+
+        let (matches, errors) = query(dir.path(), "$.language", &[QueryFile::Texts]);
+
+        assert_eq!(matches.len(), 1);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("App/config/texts/resource.en.json: "));
+    }
+}
@@ -0,0 +1,720 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Seek};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use futures::future::join_all;
+use git2::{Oid, Repository, StatusOptions};
+use regex_syntax::hir::{Class, Hir, HirKind};
+use regex_syntax::ParserBuilder;
+use serde::{Deserialize, Serialize};
+use spmc::Receiver;
+use tokio::task::{spawn_blocking, JoinError};
+
+use super::{read_text, walk, MAX_FILE_SIZE};
+use crate::analysis::LocalApp;
+
+/// Stored in the clone dir, which is skipped when discovering apps since it starts with a `.`
+pub const INDEX_DIR: &str = ".index";
+const INDEX_FILE: &str = "search.bin";
+/// Bumped whenever the format changes, older indexes are rebuilt from scratch
+const VERSION: u32 = 3;
+
+/// Trigram index over the files of all cloned apps, used to narrow down which files a regex can match.
+/// Content is not stored, candidates are read from disk and matched against the regex as usual
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct SearchIndex {
+    version: u32,
+    apps: BTreeMap<String, IndexedApp>,
+    /// Indexed by file id, `None` for files that have been removed since the last compaction
+    files: Vec<Option<IndexedFile>>,
+    /// Trigram -> sorted ids of files containing it
+    trigrams: HashMap<u32, Vec<u32>>,
+    removed: usize,
+}
+
+/// Apps are updated from the files that changed between the indexed HEAD and the current one, and the files
+/// with uncommitted changes. Apps that aren't git repos, which `clone` never creates, are rebuilt on every update
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+struct IndexedApp {
+    /// Path -> file id
+    files: BTreeMap<String, u32>,
+    /// Commit that was checked out when the app was indexed
+    head: Option<String>,
+    /// Files that had uncommitted changes or couldn't be indexed, which are reindexed on the next update
+    /// in case they have been reverted
+    dirty: BTreeSet<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexedFile {
+    pub app: String,
+    pub path: String,
+}
+
+/// Changes to a single app, computed in parallel before being applied to the index
+struct AppUpdate {
+    key: String,
+    /// Whether all files of the app were indexed from scratch
+    rebuilt: bool,
+    head: Option<String>,
+    dirty: BTreeSet<String>,
+    removed: Vec<String>,
+    indexed: Vec<(String, Vec<u32>)>,
+    /// Files that couldn't be indexed, which are retried on the next update
+    errors: Vec<String>,
+}
+
+#[derive(Default, Debug)]
+pub struct UpdateStats {
+    pub apps_rebuilt: usize,
+    pub apps_updated: usize,
+    pub apps_removed: usize,
+    pub files_indexed: usize,
+    /// `org/name` and error of files that were skipped
+    pub errors: Vec<(String, String)>,
+}
+
+impl UpdateStats {
+    pub fn is_empty(&self) -> bool {
+        self.apps_rebuilt + self.apps_updated + self.apps_removed == 0
+    }
+}
+
+/// Which trigrams a file must contain to possibly match a regex
+#[derive(Debug, Clone, PartialEq)]
+pub enum TrigramQuery {
+    All,
+    Trigrams(Vec<u32>),
+    And(Vec<TrigramQuery>),
+    Or(Vec<TrigramQuery>),
+}
+
+impl SearchIndex {
+    /// Loads the index from the clone dir, or returns an empty index if there is none or it's outdated
+    pub fn load(dir: &Path) -> Result<Self> {
+        let path = dir.join(INDEX_DIR).join(INDEX_FILE);
+        let mut file = match fs::File::open(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e).context("Failed to open search index"),
+        };
+
+        // The version is the first field, and is checked before reading the rest since the layout of older
+        // versions can't be deserialized into the current one
+        let mut version = [0; 4];
+        if file.read_exact(&mut version).is_err() || u32::from_le_bytes(version) != VERSION {
+            return Ok(Self::default());
+        }
+        file.rewind().context("Failed to read search index")?;
+
+        match bincode::deserialize_from::<_, SearchIndex>(BufReader::new(file)) {
+            Ok(index) if index.version == VERSION => Ok(index),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    pub fn save(&mut self, dir: &Path) -> Result<()> {
+        if self.removed > self.files.len() / 2 {
+            self.compact();
+        }
+        self.version = VERSION;
+
+        let index_dir = dir.join(INDEX_DIR);
+        fs::create_dir_all(&index_dir).context("Failed to create search index dir")?;
+
+        // Write to a temporary file first so that an interrupted save doesn't corrupt the index
+        let path = index_dir.join(INDEX_FILE);
+        let tmp = index_dir.join(format!("{}.tmp", INDEX_FILE));
+        let file = fs::File::create(&tmp).context("Failed to create search index")?;
+        bincode::serialize_into(BufWriter::new(file), self).context("Failed to write search index")?;
+        fs::rename(&tmp, &path).context("Failed to replace search index")?;
+
+        Ok(())
+    }
+
+    /// Brings the index up to date with the apps on disk, only reindexing the files that git reports as changed
+    pub async fn update(&mut self, apps: &[LocalApp]) -> Result<UpdateStats> {
+        let mut stats = UpdateStats::default();
+
+        let keys = apps.iter().map(|a| a.key()).collect::<HashSet<_>>();
+        let gone = self
+            .apps
+            .keys()
+            .filter(|k| !keys.contains(*k))
+            .cloned()
+            .collect::<Vec<_>>();
+        for key in gone {
+            if let Some(app) = self.apps.remove(&key) {
+                self.remove_files(app.files.into_values());
+            }
+            stats.apps_removed += 1;
+        }
+
+        let (mut tx, rx) = spmc::channel::<(LocalApp, Option<IndexedApp>)>();
+
+        let mut threads = Vec::with_capacity(num_cpus::get());
+        for _ in 0..num_cpus::get() {
+            let rx = rx.clone();
+            threads.push(spawn_blocking(move || thread(rx)));
+        }
+
+        for app in apps {
+            let previous = self.apps.get(&app.key()).cloned();
+            tx.send((app.clone(), previous)).context("Failed to queue app")?;
+        }
+
+        drop(tx);
+
+        let results = join_all(threads)
+            .await
+            .into_iter()
+            .collect::<Result<Vec<_>, JoinError>>()?;
+
+        for mut update in results.into_iter().flatten() {
+            stats
+                .errors
+                .extend(update.errors.drain(..).map(|err| (update.key.clone(), err)));
+            let unchanged = self
+                .apps
+                .get(&update.key)
+                .is_some_and(|app| app.head == update.head && app.dirty == update.dirty);
+            if !update.rebuilt && unchanged && update.removed.is_empty() && update.indexed.is_empty() {
+                continue;
+            }
+            if update.rebuilt {
+                stats.apps_rebuilt += 1;
+            } else {
+                stats.apps_updated += 1;
+            }
+            stats.files_indexed += update.indexed.len();
+            self.apply(update);
+        }
+
+        Ok(stats)
+    }
+
+    fn apply(&mut self, update: AppUpdate) {
+        let mut app = self.apps.remove(&update.key).unwrap_or_default();
+
+        let stale = update
+            .removed
+            .iter()
+            .chain(update.indexed.iter().map(|(path, _)| path))
+            .filter_map(|path| app.files.remove(path))
+            .collect::<Vec<_>>();
+        self.remove_files(stale);
+
+        for (path, trigrams) in update.indexed {
+            let id = self.files.len() as u32;
+            self.files.push(Some(IndexedFile {
+                app: update.key.clone(),
+                path: path.clone(),
+            }));
+            for trigram in trigrams {
+                self.trigrams.entry(trigram).or_default().push(id);
+            }
+            app.files.insert(path, id);
+        }
+        app.head = update.head;
+        app.dirty = update.dirty;
+
+        self.apps.insert(update.key, app);
+    }
+
+    /// Files are only marked as removed, their ids are dropped from the posting lists when compacting
+    fn remove_files(&mut self, ids: impl IntoIterator<Item = u32>) {
+        for id in ids {
+            if let Some(file) = self.files.get_mut(id as usize) {
+                if file.take().is_some() {
+                    self.removed += 1;
+                }
+            }
+        }
+    }
+
+    fn compact(&mut self) {
+        let mut mapping = vec![None; self.files.len()];
+        let mut files = Vec::with_capacity(self.files.len() - self.removed);
+        for (id, file) in std::mem::take(&mut self.files).into_iter().enumerate() {
+            if let Some(file) = file {
+                mapping[id] = Some(files.len() as u32);
+                files.push(Some(file));
+            }
+        }
+
+        for ids in self.trigrams.values_mut() {
+            *ids = ids.iter().filter_map(|id| mapping[*id as usize]).collect();
+        }
+        self.trigrams.retain(|_, ids| !ids.is_empty());
+        for app in self.apps.values_mut() {
+            for id in app.files.values_mut() {
+                *id = mapping[*id as usize].expect("Files of indexed apps are never removed");
+            }
+        }
+
+        self.files = files;
+        self.removed = 0;
+    }
+
+    pub fn file_count(&self) -> usize {
+        self.files.len() - self.removed
+    }
+
+    /// Files that may match the query, grouped by app key
+    pub fn candidates(&self, query: &TrigramQuery) -> BTreeMap<&str, Vec<&str>> {
+        let mut result = BTreeMap::<&str, Vec<&str>>::new();
+
+        let files = match self.evaluate(query) {
+            Some(ids) => ids
+                .into_iter()
+                .filter_map(|id| self.files[id as usize].as_ref())
+                .collect::<Vec<_>>(),
+            None => self.files.iter().flatten().collect(),
+        };
+        for file in files {
+            result.entry(&file.app).or_default().push(&file.path);
+        }
+
+        result
+    }
+
+    /// Sorted ids of the files matching the query, `None` meaning all files
+    fn evaluate(&self, query: &TrigramQuery) -> Option<Vec<u32>> {
+        match query {
+            TrigramQuery::All => None,
+            TrigramQuery::Trigrams(trigrams) => {
+                let mut result: Option<Vec<u32>> = None;
+                for trigram in trigrams {
+                    let ids = self.trigrams.get(trigram).map(|ids| ids.as_slice()).unwrap_or_default();
+                    result = Some(match result {
+                        Some(result) => intersect(&result, ids),
+                        None => ids.to_vec(),
+                    });
+                }
+                result
+            }
+            TrigramQuery::And(queries) => {
+                let mut result: Option<Vec<u32>> = None;
+                for ids in queries.iter().filter_map(|q| self.evaluate(q)) {
+                    result = Some(match result {
+                        Some(result) => intersect(&result, &ids),
+                        None => ids,
+                    });
+                }
+                result
+            }
+            TrigramQuery::Or(queries) => {
+                let mut result = Vec::new();
+                for query in queries {
+                    result.extend(self.evaluate(query)?);
+                }
+                result.sort_unstable();
+                result.dedup();
+                Some(result)
+            }
+        }
+    }
+}
+
+fn intersect(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len().min(b.len()));
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                result.push(a[i]);
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    result
+}
+
+fn thread(rx: Receiver<(LocalApp, Option<IndexedApp>)>) -> Vec<AppUpdate> {
+    let mut result = Vec::new();
+
+    while let Ok((app, previous)) = rx.recv() {
+        result.push(app_update(&app, previous));
+    }
+
+    result
+}
+
+/// Computes what changed in an app since it was last indexed, from git when possible and otherwise by indexing
+/// the same files as searching without the index
+fn app_update(app: &LocalApp, previous: Option<IndexedApp>) -> AppUpdate {
+    let repo = Repository::open(&app.dir).ok();
+    let head = repo
+        .as_ref()
+        .and_then(|r| r.head().ok()?.peel_to_commit().ok())
+        .map(|c| c.id());
+
+    let mut update = AppUpdate {
+        key: app.key(),
+        rebuilt: false,
+        head: head.map(|h| h.to_string()),
+        dirty: BTreeSet::new(),
+        removed: Vec::new(),
+        indexed: Vec::new(),
+        errors: Vec::new(),
+    };
+
+    // Anything that goes wrong when asking git falls back to rebuilding the app
+    let dirty = repo.as_ref().and_then(|r| dirty_files(r).ok());
+    let changes = match (&repo, head, &previous, &dirty) {
+        (Some(repo), Some(head), Some(previous), Some(_)) => changed_files(repo, previous, head).ok(),
+        _ => None,
+    };
+    update.dirty = dirty.unwrap_or_default();
+
+    let paths = match (changes, &repo) {
+        (Some(changes), Some(repo)) => {
+            let (searched, removed) = changes
+                .into_iter()
+                .chain(update.dirty.iter().cloned())
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .partition(|p| is_searched(repo, &app.dir, p));
+            update.removed = removed;
+            searched
+        }
+        _ => {
+            let (paths, errors) = walk(&app.dir);
+            update.rebuilt = true;
+            update.errors = errors;
+            update.removed = previous.map(|p| p.files.into_keys().collect()).unwrap_or_default();
+            paths
+        }
+    };
+
+    for path in paths {
+        match index_file(&app.dir.join(&path)) {
+            Ok(Some(trigrams)) => update.indexed.push((path, trigrams)),
+            // Binary and large files are kept without trigrams, and are only read again when git reports a change
+            Ok(None) => update.indexed.push((path, Vec::new())),
+            Err(err) => {
+                update.errors.push(format!("{:#}", err));
+                update.dirty.insert(path.clone());
+                update.removed.push(path);
+            }
+        }
+    }
+
+    update
+}
+
+/// Files that changed between the indexed HEAD and the given one, and the files that were dirty when indexed.
+/// Fails if the indexed HEAD is gone, e.g. after a force push, so that the app is rebuilt
+fn changed_files(repo: &Repository, previous: &IndexedApp, head: Oid) -> Result<BTreeSet<String>> {
+    let indexed = previous.head.as_deref().context("Not indexed from git before")?;
+    let indexed = repo
+        .find_commit(Oid::from_str(indexed)?)
+        .context("Indexed commit not found")?;
+
+    let mut result = previous.dirty.clone();
+    if indexed.id() == head {
+        return Ok(result);
+    }
+
+    let head = repo.find_commit(head)?;
+    let diff = repo
+        .diff_tree_to_tree(Some(&indexed.tree()?), Some(&head.tree()?), None)
+        .context("Failed to diff commits")?;
+    for delta in diff.deltas() {
+        for file in [delta.old_file(), delta.new_file()] {
+            if let Some(path) = file.path().and_then(|p| p.to_str()) {
+                result.insert(path.to_string());
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+/// Files with uncommitted changes, including new files that aren't ignored
+fn dirty_files(repo: &Repository) -> Result<BTreeSet<String>> {
+    let mut options = StatusOptions::new();
+    options
+        .include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .include_ignored(false)
+        .exclude_submodules(true);
+
+    let statuses = repo.statuses(Some(&mut options)).context("Failed to read git status")?;
+    Ok(statuses
+        .iter()
+        .filter_map(|entry| entry.path().map(|p| p.to_string()))
+        .collect())
+}
+
+/// Whether a file changed according to git exists and would be found by `walk`, which skips hidden and ignored files
+fn is_searched(repo: &Repository, app_dir: &Path, path: &str) -> bool {
+    !path.split('/').any(|part| part.starts_with('.'))
+        && !repo.is_path_ignored(path).unwrap_or(false)
+        && app_dir.join(path).is_file()
+}
+
+/// Trigrams of a file, `None` if it doesn't exist or isn't searched
+fn index_file(path: &Path) -> Result<Option<Vec<u32>>> {
+    match fs::metadata(path) {
+        Ok(metadata) if metadata.is_file() && metadata.len() <= MAX_FILE_SIZE => {}
+        Ok(_) => return Ok(None),
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    }
+
+    Ok(read_text(path)?.map(|content| trigrams(content.as_bytes())))
+}
+
+/// Sorted unique trigrams, ASCII case insensitive so that the same index works for case insensitive searches
+fn trigrams(content: &[u8]) -> Vec<u32> {
+    let mut result = content
+        .windows(3)
+        .map(|w| trigram(w[0], w[1], w[2]))
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    result.sort_unstable();
+    result
+}
+
+fn trigram(a: u8, b: u8, c: u8) -> u32 {
+    (a.to_ascii_lowercase() as u32) << 16 | (b.to_ascii_lowercase() as u32) << 8 | c.to_ascii_lowercase() as u32
+}
+
+/// Derives the trigrams required by a regex from its literal parts
+pub fn trigram_query(pattern: &str, ignore_case: bool) -> Result<TrigramQuery> {
+    let hir = ParserBuilder::new()
+        .case_insensitive(ignore_case)
+        .build()
+        .parse(pattern)
+        .context("Invalid search pattern")?;
+
+    Ok(required(&hir))
+}
+
+fn required(hir: &Hir) -> TrigramQuery {
+    match hir.kind() {
+        HirKind::Literal(literal) => literal_query(&literal.0),
+        HirKind::Capture(capture) => required(&capture.sub),
+        HirKind::Repetition(repetition) if repetition.min > 0 => required(&repetition.sub),
+        HirKind::Concat(hirs) => {
+            let mut queries = Vec::new();
+            let mut run = Vec::new();
+            for hir in hirs {
+                match hir.kind() {
+                    HirKind::Literal(literal) => run.extend_from_slice(&literal.0),
+                    HirKind::Class(class) if single_byte(class).is_some() => run.extend(single_byte(class)),
+                    // Zero width, so the literals around it are still adjacent
+                    HirKind::Look(_) | HirKind::Empty => {}
+                    _ => {
+                        queries.push(literal_query(&std::mem::take(&mut run)));
+                        queries.push(required(hir));
+                    }
+                }
+            }
+            queries.push(literal_query(&run));
+            and(queries)
+        }
+        HirKind::Alternation(hirs) => {
+            let queries = hirs.iter().map(required).collect::<Vec<_>>();
+            if queries.contains(&TrigramQuery::All) {
+                TrigramQuery::All
+            } else {
+                TrigramQuery::Or(queries)
+            }
+        }
+        _ => TrigramQuery::All,
+    }
+}
+
+/// ASCII classes that only differ by case, like `[aA]` from case insensitive patterns, act as literals
+fn single_byte(class: &Class) -> Option<u8> {
+    let chars = match class {
+        Class::Unicode(class) => class
+            .iter()
+            .flat_map(|r| (r.start() as u32..=r.end() as u32).take(4))
+            .filter_map(char::from_u32)
+            .collect::<Vec<_>>(),
+        Class::Bytes(class) => class
+            .iter()
+            .flat_map(|r| (r.start()..=r.end()).take(4))
+            .map(char::from)
+            .collect(),
+    };
+    if chars.is_empty() || chars.len() > 3 {
+        return None;
+    }
+
+    // The index only folds ASCII, so a class that also matches e.g. the Kelvin sign in `(?i)k` must not be
+    // narrowed down to the trigrams of `k`
+    if !chars.iter().all(|c| c.is_ascii()) {
+        return None;
+    }
+
+    let lower = chars.iter().map(|c| c.to_ascii_lowercase()).collect::<HashSet<_>>();
+    match lower.into_iter().collect::<Vec<_>>()[..] {
+        [c] => Some(c as u8),
+        _ => None,
+    }
+}
+
+fn literal_query(bytes: &[u8]) -> TrigramQuery {
+    if bytes.len() < 3 {
+        return TrigramQuery::All;
+    }
+    TrigramQuery::Trigrams(trigrams(bytes))
+}
+
+fn and(queries: Vec<TrigramQuery>) -> TrigramQuery {
+    let mut queries = queries
+        .into_iter()
+        .filter(|q| *q != TrigramQuery::All)
+        .collect::<Vec<_>>();
+    match queries.len() {
+        0 => TrigramQuery::All,
+        1 => queries.remove(0),
+        _ => TrigramQuery::And(queries),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn literal(s: &str) -> TrigramQuery {
+        TrigramQuery::Trigrams(trigrams(s.as_bytes()))
+    }
+
+    #[test]
+    fn literals_require_their_trigrams() {
+        assert_eq!(
+            trigram_query("IDataProcessor", false).unwrap(),
+            literal("IDataProcessor")
+        );
+        assert_eq!(trigram_query("ab", false).unwrap(), TrigramQuery::All);
+    }
+
+    #[test]
+    fn case_insensitive_literals_use_the_same_trigrams() {
+        assert_eq!(trigram_query("IDataWriter", true).unwrap(), literal("idatawriter"));
+        // `s` also matches the long s, which isn't folded in the index
+        assert_eq!(trigram_query("IDataProcessor", true).unwrap(), literal("idataproce"));
+    }
+
+    #[test]
+    fn optional_parts_split_the_literals() {
+        assert_eq!(
+            trigram_query(r"class\s+\w+Validator", false).unwrap(),
+            TrigramQuery::And(vec![literal("class"), literal("Validator")])
+        );
+        assert_eq!(
+            trigram_query("foo.*bar", false).unwrap(),
+            TrigramQuery::And(vec![literal("foo"), literal("bar")])
+        );
+    }
+
+    #[test]
+    fn alternations_require_either_side() {
+        assert_eq!(
+            trigram_query("IDataProcessor|IDataWriteProcessor", false).unwrap(),
+            TrigramQuery::Or(vec![literal("IDataProcessor"), literal("IDataWriteProcessor")])
+        );
+        assert_eq!(trigram_query("IDataProcessor|ab", false).unwrap(), TrigramQuery::All);
+    }
+
+    #[test]
+    fn case_insensitive_searches_find_non_ascii_case_variants() {
+        let mut index = SearchIndex::default();
+        index.apply(AppUpdate {
+            key: "ttd/app".to_string(),
+            rebuilt: true,
+            head: None,
+            dirty: BTreeSet::new(),
+            removed: Vec::new(),
+            indexed: vec![("App/kelvin.txt".to_string(), trigrams("200 \u{212A}elvin".as_bytes()))],
+            errors: Vec::new(),
+        });
+
+        let query = trigram_query("(?i)kelvin", false).unwrap();
+
+        assert_eq!(query, literal("elvin"));
+        assert_eq!(
+            index.candidates(&query),
+            BTreeMap::from([("ttd/app", vec!["App/kelvin.txt"])])
+        );
+    }
+
+    fn commit(repo: &Repository) {
+        let mut index = repo.index().unwrap();
+        index.add_all(["*"], git2::IndexAddOption::DEFAULT, None).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("Test", "test@example.com").unwrap();
+        let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let parents = parent.iter().collect::<Vec<_>>();
+        repo.commit(Some("HEAD"), &signature, &signature, "Commit", &tree, &parents)
+            .unwrap();
+    }
+
+    fn paths(update: &AppUpdate) -> Vec<&str> {
+        update.indexed.iter().map(|(path, _)| path.as_str()).collect()
+    }
+
+    #[test]
+    fn updates_from_the_files_git_reports_as_changed() {
+        let dir = tempfile::tempdir().unwrap();
+        let app = LocalApp {
+            org: "ttd".to_string(),
+            name: "app".to_string(),
+            dir: dir.path().to_path_buf(),
+        };
+        let write = |path: &str, content: &str| {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        };
+        let repo = Repository::init(dir.path()).unwrap();
+        write(".gitignore", "bin/\n");
+        write("App/Alpha.cs", "class Alpha {}");
+        write("App/Gamma.cs", "class Gamma {}");
+        commit(&repo);
+
+        let mut index = SearchIndex::default();
+        let update = app_update(&app, None);
+        assert!(update.rebuilt);
+        assert_eq!(paths(&update), vec!["App/Alpha.cs", "App/Gamma.cs"]);
+        index.apply(update);
+
+        // Committed, uncommitted, ignored and deleted changes
+        write("App/Beta.cs", "class Beta {}");
+        fs::remove_file(dir.path().join("App/Gamma.cs")).unwrap();
+        commit(&repo);
+        write("App/Alpha.cs", "class AlphaEdited {}");
+        write("bin/Generated.cs", "class Generated {}");
+
+        let update = app_update(&app, index.apps.get("ttd/app").cloned());
+        assert!(!update.rebuilt);
+        assert_eq!(paths(&update), vec!["App/Alpha.cs", "App/Beta.cs"]);
+        assert_eq!(update.removed, vec!["App/Gamma.cs"]);
+        assert_eq!(update.dirty, BTreeSet::from(["App/Alpha.cs".to_string()]));
+        index.apply(update);
+        let candidates = index.candidates(&trigram_query("AlphaEdited|Beta", false).unwrap());
+        assert_eq!(candidates["ttd/app"], vec!["App/Alpha.cs", "App/Beta.cs"]);
+
+        // Reverted edits are reindexed, since they were dirty
+        write("App/Alpha.cs", "class Alpha {}");
+        let update = app_update(&app, index.apps.get("ttd/app").cloned());
+        assert_eq!(paths(&update), vec!["App/Alpha.cs"]);
+        assert!(update.dirty.is_empty());
+        index.apply(update);
+
+        let update = app_update(&app, index.apps.get("ttd/app").cloned());
+        assert!(update.indexed.is_empty() && update.removed.is_empty());
+    }
+}
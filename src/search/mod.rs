@@ -0,0 +1,353 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
+
+use anyhow::{Context, Result};
+use futures::future::join_all;
+use git2::Repository;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::WalkBuilder;
+use regex::{Regex, RegexBuilder};
+use spmc::Receiver;
+use tokio::task::{spawn_blocking, JoinError};
+
+use crate::analysis::runner::Upstream;
+use crate::analysis::{discover, LocalApp};
+use crate::configuration::Configuration;
+use crate::gitea_client::GiteaRepo;
+use index::SearchIndex;
+
+pub mod index;
+
+/// Files larger than this are most likely generated or data, and not worth searching
+const MAX_FILE_SIZE: u64 = 10 * 1024 * 1024;
+/// Same heuristic as git and ripgrep, a NUL byte early in the file means it's binary
+const BINARY_CHECK_LEN: usize = 8000;
+const MAX_LINE_LEN: usize = 200;
+
+/// Which files to search and what to look for
+pub struct Query {
+    pub regex: Regex,
+    pub ignore_case: bool,
+    pub orgs: Vec<String>,
+    pub globs: Option<GlobSet>,
+    /// Only search files whose path relative to the app starts with this
+    pub path: Option<String>,
+}
+
+impl Query {
+    pub fn new(
+        pattern: &str,
+        ignore_case: bool,
+        orgs: &[String],
+        globs: &[String],
+        path: Option<&str>,
+    ) -> Result<Self> {
+        let regex = RegexBuilder::new(pattern)
+            .case_insensitive(ignore_case)
+            .build()
+            .context("Invalid search pattern")?;
+
+        let globs = if globs.is_empty() {
+            None
+        } else {
+            let mut builder = GlobSetBuilder::new();
+            for glob in globs {
+                builder.add(Glob::new(glob).with_context(|| format!("Invalid glob {}", glob))?);
+            }
+            Some(builder.build().context("Failed to build globs")?)
+        };
+
+        Ok(Query {
+            regex,
+            ignore_case,
+            orgs: orgs.to_vec(),
+            globs,
+            path: path.map(|p| p.trim_matches('/').to_string()),
+        })
+    }
+
+    pub fn includes_app(&self, app: &LocalApp) -> bool {
+        self.orgs.is_empty() || self.orgs.contains(&app.org)
+    }
+
+    /// Whether a file should be searched, based on its path relative to the app dir
+    pub fn includes_path(&self, path: &str) -> bool {
+        self.path.as_deref().is_none_or(|p| path.starts_with(p)) && self.globs.as_ref().is_none_or(|g| g.is_match(path))
+    }
+}
+
+pub struct AppMatches {
+    pub app: LocalApp,
+    /// Commit that was searched, used to link to the exact lines that matched
+    pub commit: Option<String>,
+    pub matches: Vec<Match>,
+    /// Files that couldn't be searched, with the reason
+    pub errors: Vec<String>,
+}
+
+pub struct Match {
+    pub path: String,
+    /// 1-based, like in editors and the Gitea web UI
+    pub line: usize,
+    pub text: String,
+}
+
+pub async fn run(config: &'static Configuration, query: Query, no_index: bool, gitea_links: bool) -> Result<()> {
+    // Links are derived from the base url, unless asked to point to the repos as Gitea knows them
+    let repos = if gitea_links {
+        Upstream::fetch(config, false).await?.repos
+    } else {
+        BTreeMap::new()
+    };
+
+    let apps = if no_index {
+        let dir = config.dir.clone();
+        spawn_blocking(move || discover(&dir))
+            .await
+            .context("Failed to wait for discovery")??
+            .into_iter()
+            .filter(|app| query.includes_app(app))
+            .map(|app| (app, None))
+            .collect()
+    } else {
+        let (apps, index) = update_index(config).await?;
+        let candidates = index.candidates(&index::trigram_query(query.regex.as_str(), query.ignore_case)?);
+        println!(
+            "Candidate files from the search index: {}",
+            candidates.values().map(|files| files.len()).sum::<usize>()
+        );
+
+        apps.into_iter()
+            .filter(|app| query.includes_app(app))
+            .filter_map(|app| {
+                let files = candidates.get(app.key().as_str())?;
+                let files = files.iter().map(|f| f.to_string()).collect();
+                Some((app, Some(files)))
+            })
+            .collect()
+    };
+
+    let results = search(apps, query).await?;
+
+    print_results(config, &repos, &results);
+
+    Ok(())
+}
+
+/// Brings the search index of the cloned repos up to date, returning the apps and the index
+pub async fn update_index(config: &'static Configuration) -> Result<(Vec<LocalApp>, SearchIndex)> {
+    let dir = config.dir.clone();
+    let (apps, mut index) = spawn_blocking(move || Ok::<_, anyhow::Error>((discover(&dir)?, SearchIndex::load(&dir)?)))
+        .await
+        .context("Failed to wait for loading the search index")??;
+
+    let start = Instant::now();
+    let stats = index.update(&apps).await.context("Failed to update search index")?;
+    for (app, err) in &stats.errors {
+        println!("WARNING: skipped file in {} when indexing: {}", app, err);
+    }
+    if stats.is_empty() {
+        return Ok((apps, index));
+    }
+
+    let dir = config.dir.clone();
+    let index = spawn_blocking(move || index.save(&dir).map(|_| index))
+        .await
+        .context("Failed to wait for saving the search index")??;
+
+    println!(
+        "Updated search index in {:.1}s - rebuilt={} updated={} removed={} files indexed={} total={}",
+        start.elapsed().as_secs_f64(),
+        stats.apps_rebuilt,
+        stats.apps_updated,
+        stats.apps_removed,
+        stats.files_indexed,
+        index.file_count()
+    );
+
+    Ok((apps, index))
+}
+
+/// Searches all apps in parallel, returning the apps with matches sorted by org and name.
+/// Apps can be restricted to a list of candidate files, otherwise all their files are searched
+pub async fn search(apps: Vec<(LocalApp, Option<Vec<String>>)>, query: Query) -> Result<Vec<AppMatches>> {
+    let query = Arc::new(query);
+
+    let (mut tx, rx) = spmc::channel::<(LocalApp, Option<Vec<String>>)>();
+
+    let mut threads = Vec::with_capacity(num_cpus::get());
+    for _ in 0..num_cpus::get() {
+        let rx = rx.clone();
+        let query = query.clone();
+
+        threads.push(spawn_blocking(move || thread(rx, &query)));
+    }
+
+    for app in apps {
+        tx.send(app).context("Failed to queue app")?;
+    }
+
+    drop(tx);
+
+    let results = join_all(threads)
+        .await
+        .into_iter()
+        .collect::<Result<Vec<_>, JoinError>>()?;
+
+    let mut result = results.into_iter().flatten().collect::<Vec<_>>();
+    result.sort_by(|a, b| a.app.org.cmp(&b.app.org).then_with(|| a.app.name.cmp(&b.app.name)));
+
+    Ok(result)
+}
+
+fn thread(rx: Receiver<(LocalApp, Option<Vec<String>>)>, query: &Query) -> Vec<AppMatches> {
+    let mut result = Vec::new();
+
+    while let Ok((app, files)) = rx.recv() {
+        let (files, mut errors) = match files {
+            Some(files) => (files, Vec::new()),
+            None => walk(&app.dir),
+        };
+        let (matches, search_errors) = search_app(&app.dir, &files, query);
+        errors.extend(search_errors);
+        if matches.is_empty() && errors.is_empty() {
+            continue;
+        }
+
+        let commit = Repository::open(&app.dir)
+            .ok()
+            .and_then(|r| r.head().ok()?.peel_to_commit().ok().map(|c| c.id().to_string()));
+        result.push(AppMatches {
+            app,
+            commit,
+            matches,
+            errors,
+        });
+    }
+
+    result
+}
+
+/// Searches the given files of an app, skipping binary files.
+/// Files that can't be read are returned as errors instead of failing the search
+pub fn search_app(app_dir: &Path, files: &[String], query: &Query) -> (Vec<Match>, Vec<String>) {
+    let mut result = Vec::new();
+    let mut errors = Vec::new();
+
+    for path in files.iter().filter(|p| query.includes_path(p)) {
+        let full_path = app_dir.join(path);
+        // Files in the index can have been deleted since it was updated
+        if fs::metadata(&full_path).map_or(true, |m| !m.is_file() || m.len() > MAX_FILE_SIZE) {
+            continue;
+        }
+
+        let content = match read_text(&full_path) {
+            Ok(Some(content)) => content,
+            Ok(None) => continue,
+            Err(err) => {
+                errors.push(format!("{:#}", err));
+                continue;
+            }
+        };
+
+        for (i, line) in content.lines().enumerate() {
+            if query.regex.is_match(line) {
+                result.push(Match {
+                    path: path.clone(),
+                    line: i + 1,
+                    text: truncate(line.trim(), MAX_LINE_LEN).to_string(),
+                });
+            }
+        }
+    }
+
+    result.sort_by(|a, b| a.path.cmp(&b.path).then_with(|| a.line.cmp(&b.line)));
+
+    (result, errors)
+}
+
+/// Files of an app that are searched, respecting .gitignore, and the errors from dirs that couldn't be read
+pub fn walk(app_dir: &Path) -> (Vec<String>, Vec<String>) {
+    let mut files = Vec::new();
+    let mut errors = Vec::new();
+
+    // Apps aren't necessarily git repos, but their .gitignore files should still be respected
+    for entry in WalkBuilder::new(app_dir).require_git(false).build() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                errors.push(err.to_string());
+                continue;
+            }
+        };
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        if let Ok(path) = entry.path().strip_prefix(app_dir) {
+            files.push(path.to_string_lossy().replace('\\', "/"));
+        }
+    }
+
+    (files, errors)
+}
+
+/// Reads a file as text, returning `None` if it's binary
+pub fn read_text(path: &Path) -> Result<Option<String>> {
+    let bytes = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    if bytes[..bytes.len().min(BINARY_CHECK_LEN)].contains(&0) {
+        return Ok(None);
+    }
+
+    Ok(Some(String::from_utf8_lossy(&bytes).into_owned()))
+}
+
+fn truncate(s: &str, max: usize) -> &str {
+    match s.char_indices().nth(max) {
+        Some((i, _)) => &s[..i],
+        None => s,
+    }
+}
+
+fn print_results(config: &Configuration, repos: &BTreeMap<String, GiteaRepo>, results: &[AppMatches]) {
+    let mut files = 0;
+    let mut matches = 0;
+
+    for app in results.iter().filter(|r| !r.matches.is_empty()) {
+        let repo = repos
+            .get(&app.app.key())
+            .cloned()
+            .unwrap_or_else(|| app.app.gitea_repo(&config.base_url));
+        let reference = app.commit.as_deref().unwrap_or("master");
+
+        println!("{} ({} matches)", app.app.key(), app.matches.len());
+        let mut previous = None;
+        for m in &app.matches {
+            println!("  {}:{}: {}", m.path, m.line, m.text);
+            println!("    {}", repo.web_link(reference, &m.path, Some(m.line)));
+
+            files += usize::from(previous != Some(&m.path));
+            previous = Some(&m.path);
+        }
+        matches += app.matches.len();
+    }
+
+    println!("--------------------------------------------------");
+    println!(
+        "{} matches in {} files across {} apps",
+        matches,
+        files,
+        results.iter().filter(|r| !r.matches.is_empty()).count()
+    );
+
+    let failed = results.iter().flat_map(|r| r.errors.iter().map(move |e| (&r.app, e)));
+    let failed = failed.collect::<Vec<_>>();
+    if !failed.is_empty() {
+        println!("Failed to search ({}):", failed.len());
+        for (app, err) in failed {
+            println!("  {}: {}", app.key(), err);
+        }
+    }
+}
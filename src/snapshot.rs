@@ -0,0 +1,499 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use tokio::task::spawn_blocking;
+
+use crate::analysis::compare_versions;
+use crate::configuration::Configuration;
+use crate::inventory::{self, Inventory};
+
+pub const SNAPSHOT_DIR: &str = ".snapshots";
+/// Sorts chronologically and is a valid file name on all platforms
+const FILE_NAME_FORMAT: &str = "%Y-%m-%dT%H%M%SZ";
+
+/// Collects the inventory and stores it as a new snapshot
+pub async fn run(config: &'static Configuration, offline: bool) -> Result<()> {
+    println!("Taking snapshot of: {}", config.dir.display());
+
+    let inventory = inventory::collect(config, offline, None).await?;
+
+    println!("Number of orgs: {}", inventory.orgs.len());
+    println!("Number of repos: {}", inventory.repos.len());
+    println!("Number of findings: {}", inventory.report.findings.len());
+    println!("--------------------------------------------------");
+
+    let path = spawn_blocking(move || save(&config.dir, &inventory))
+        .await
+        .context("Failed to wait for saving snapshot")??;
+    println!("Saved snapshot to {}", path.display());
+
+    Ok(())
+}
+
+/// Stores the inventory as `<dir>/.snapshots/<created>.json`, returning the path
+pub fn save(dir: &Path, inventory: &Inventory) -> Result<PathBuf> {
+    let snapshot_dir = dir.join(SNAPSHOT_DIR);
+    fs::create_dir_all(&snapshot_dir).context("Failed to create snapshot dir")?;
+
+    let path = snapshot_dir.join(format!("{}.json", inventory.created.format(FILE_NAME_FORMAT)));
+    let json = serde_json::to_vec(inventory).context("Failed to serialize snapshot")?;
+    fs::write(&path, json).with_context(|| format!("Failed to write snapshot {}", path.display()))?;
+
+    Ok(path)
+}
+
+/// All snapshots, oldest first
+pub fn list(dir: &Path) -> Result<Vec<PathBuf>> {
+    let entries = match fs::read_dir(dir.join(SNAPSHOT_DIR)) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).context("Failed to read snapshot dir"),
+    };
+
+    let mut result = Vec::new();
+    for entry in entries {
+        let path = entry.context("Failed to read snapshot dir")?.path();
+        if path.extension().is_some_and(|e| e == "json") {
+            result.push(path);
+        }
+    }
+    result.sort();
+
+    Ok(result)
+}
+
+pub fn load(path: &Path) -> Result<Inventory> {
+    let json = fs::read(path).with_context(|| format!("Failed to read snapshot {}", path.display()))?;
+    serde_json::from_slice(&json).with_context(|| format!("Failed to parse snapshot {}", path.display()))
+}
+
+/// Finds a snapshot by path, or by the start of its name, e.g. `2024-05-01`.
+/// If several snapshots match, the latest one is used
+fn resolve(snapshots: &[PathBuf], name: &str) -> Result<PathBuf> {
+    let path = PathBuf::from(name);
+    if path.is_file() {
+        return Ok(path);
+    }
+
+    snapshots
+        .iter()
+        .rev()
+        .find(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with(name))
+        })
+        .cloned()
+        .ok_or_else(|| anyhow!("No snapshot matching {}", name))
+}
+
+/// What changed between two snapshots
+#[derive(Default, Debug)]
+pub struct SnapshotDiff {
+    pub orgs_added: Vec<String>,
+    pub orgs_removed: Vec<String>,
+    pub apps_added: Vec<String>,
+    pub apps_removed: Vec<String>,
+    /// App key with the version before and after, for apps analyzed in both snapshots
+    pub app_lib_changes: Vec<(String, Option<String>, Option<String>)>,
+    pub frontend_changes: Vec<(String, Option<String>, Option<String>)>,
+    /// Number of analyzed apps per major version, before and after
+    pub app_lib_majors: BTreeMap<String, (usize, usize)>,
+    pub frontend_majors: BTreeMap<String, (usize, usize)>,
+    /// Component types with the apps that started using them
+    pub new_components: BTreeMap<String, Vec<String>>,
+}
+
+pub fn diff(from: &Inventory, to: &Inventory) -> SnapshotDiff {
+    let mut result = SnapshotDiff::default();
+
+    let from_orgs = from.orgs.iter().map(|o| o.name.as_str()).collect::<BTreeSet<_>>();
+    let to_orgs = to.orgs.iter().map(|o| o.name.as_str()).collect::<BTreeSet<_>>();
+    result.orgs_added = to_orgs.difference(&from_orgs).map(|o| o.to_string()).collect();
+    result.orgs_removed = from_orgs.difference(&to_orgs).map(|o| o.to_string()).collect();
+
+    let from_apps = from.repos.iter().map(|r| r.key()).collect::<BTreeSet<_>>();
+    let to_apps = to.repos.iter().map(|r| r.key()).collect::<BTreeSet<_>>();
+    result.apps_added = to_apps.difference(&from_apps).cloned().collect();
+    result.apps_removed = from_apps.difference(&to_apps).cloned().collect();
+
+    let from_findings = from.findings_by_app();
+    let to_findings = to.findings_by_app();
+    let no_findings = Vec::new();
+
+    for (snapshot, findings, after) in [(from, &from_findings, false), (to, &to_findings, true)] {
+        for app in &snapshot.report.apps {
+            let findings = findings.get(app).unwrap_or(&no_findings);
+            count_major(&mut result.app_lib_majors, inventory::app_lib_version(findings), after);
+            count_major(
+                &mut result.frontend_majors,
+                inventory::frontend_version(findings),
+                after,
+            );
+        }
+    }
+
+    let analyzed_before = from.report.apps.iter().collect::<BTreeSet<_>>();
+    for app in to.report.apps.iter().filter(|a| analyzed_before.contains(a)) {
+        let before = from_findings.get(app).unwrap_or(&no_findings);
+        let after = to_findings.get(app).unwrap_or(&no_findings);
+
+        let versions = (inventory::app_lib_version(before), inventory::app_lib_version(after));
+        if versions.0 != versions.1 {
+            result.app_lib_changes.push(change(app, versions));
+        }
+        let versions = (inventory::frontend_version(before), inventory::frontend_version(after));
+        if versions.0 != versions.1 {
+            result.frontend_changes.push(change(app, versions));
+        }
+
+        let components_before = inventory::component_types(before);
+        for component in inventory::component_types(after).difference(&components_before) {
+            result
+                .new_components
+                .entry(component.to_string())
+                .or_default()
+                .push(app.clone());
+        }
+    }
+
+    result
+}
+
+fn change(app: &str, (from, to): (Option<&str>, Option<&str>)) -> (String, Option<String>, Option<String>) {
+    (app.to_string(), from.map(|v| v.to_string()), to.map(|v| v.to_string()))
+}
+
+fn count_major(majors: &mut BTreeMap<String, (usize, usize)>, version: Option<&str>, after: bool) {
+    let counts = majors.entry(major(version)).or_default();
+    if after {
+        counts.1 += 1;
+    } else {
+        counts.0 += 1;
+    }
+}
+
+/// Major part of a version, `-` for unknown versions and `other` for anything that isn't a version
+fn major(version: Option<&str>) -> String {
+    let Some(version) = version else {
+        return "-".to_string();
+    };
+    match version.split('.').next() {
+        Some(major) if !major.is_empty() && major.chars().all(|c| c.is_ascii_digit()) => major.to_string(),
+        _ => "other".to_string(),
+    }
+}
+
+/// Compares two snapshots, by default the two latest
+pub async fn run_diff(config: &'static Configuration, from: Option<&str>, to: Option<&str>) -> Result<()> {
+    let from = from.map(|f| f.to_string());
+    let to = to.map(|t| t.to_string());
+
+    spawn_blocking(move || {
+        let snapshots = list(&config.dir)?;
+        let to = match &to {
+            Some(name) => resolve(&snapshots, name)?,
+            None => snapshots
+                .last()
+                .cloned()
+                .ok_or_else(|| anyhow!("There are no snapshots"))?,
+        };
+        let from = match &from {
+            Some(name) => resolve(&snapshots, name)?,
+            None => match snapshots.iter().rev().find(|s| **s < to) {
+                Some(snapshot) => snapshot.clone(),
+                None => bail!("There is no snapshot before {}", to.display()),
+            },
+        };
+
+        let before = load(&from)?;
+        let after = load(&to)?;
+
+        println!("Comparing: {} -> {}", from.display(), to.display());
+        println!("Created: {} -> {}", before.created, after.created);
+        println!("Number of repos: {} -> {}", before.repos.len(), after.repos.len());
+        println!("--------------------------------------------------");
+
+        print_diff(&diff(&before, &after));
+
+        Ok(())
+    })
+    .await
+    .context("Failed to wait for diff")?
+}
+
+fn print_diff(diff: &SnapshotDiff) {
+    print_list("Orgs added", &diff.orgs_added);
+    print_list("Orgs removed", &diff.orgs_removed);
+    print_list("Apps added", &diff.apps_added);
+    print_list("Apps removed", &diff.apps_removed);
+
+    println!("--------------------------------------------------");
+    print_majors("App-lib major versions", &diff.app_lib_majors);
+    print_changes("App-lib version changes", &diff.app_lib_changes);
+
+    println!("--------------------------------------------------");
+    print_majors("Frontend major versions", &diff.frontend_majors);
+    print_changes("Frontend version changes", &diff.frontend_changes);
+
+    println!("--------------------------------------------------");
+    let adopted = diff.new_components.values().map(|apps| apps.len()).sum::<usize>();
+    println!("Newly adopted components ({}):", adopted);
+    let mut components = diff.new_components.iter().collect::<Vec<_>>();
+    components.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then_with(|| a.0.cmp(b.0)));
+    for (component, apps) in components {
+        println!("  {:<40} {:>5} apps: {}", component, apps.len(), apps.join(", "));
+    }
+}
+
+fn print_list(title: &str, items: &[String]) {
+    println!("{} ({}):", title, items.len());
+    for item in items {
+        println!("  {}", item);
+    }
+}
+
+fn print_majors(title: &str, majors: &BTreeMap<String, (usize, usize)>) {
+    println!("{}:", title);
+    let mut majors = majors.iter().collect::<Vec<_>>();
+    majors.sort_by(|a, b| compare_versions(a.0, b.0));
+    for (major, (before, after)) in majors {
+        println!(
+            "  {:<10} {:>5} -> {:<5} ({:+})",
+            major,
+            before,
+            after,
+            *after as i64 - *before as i64
+        );
+    }
+}
+
+fn print_changes(title: &str, changes: &[(String, Option<String>, Option<String>)]) {
+    let upgrades = changes
+        .iter()
+        .filter(|(_, from, to)| matches!((from, to), (Some(from), Some(to)) if compare_versions(from, to).is_lt()))
+        .count();
+    println!("{} ({}, {} upgrades):", title, changes.len(), upgrades);
+
+    let mut by_change = HashMap::<(&str, &str), Vec<&str>>::new();
+    for (app, from, to) in changes {
+        by_change
+            .entry((from.as_deref().unwrap_or("-"), to.as_deref().unwrap_or("-")))
+            .or_default()
+            .push(app);
+    }
+    let mut by_change = by_change.into_iter().collect::<Vec<_>>();
+    by_change.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then_with(|| a.0.cmp(&b.0)));
+    for ((from, to), apps) in by_change {
+        println!("  {} -> {} ({}): {}", from, to, apps.len(), apps.join(", "));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::analysis::analyzer::{Finding, Report, Severity};
+    use crate::inventory::{Org, Repo};
+
+    /// A finding as app key, analyzer, kind, name and value
+    type Fixture = (
+        &'static str,
+        &'static str,
+        &'static str,
+        &'static str,
+        Option<&'static str>,
+    );
+
+    fn snapshot(orgs: &[&str], apps: &[&str], findings: &[Fixture]) -> Inventory {
+        Inventory {
+            created: Utc::now(),
+            orgs: orgs
+                .iter()
+                .map(|o| Org {
+                    name: o.to_string(),
+                    ..Default::default()
+                })
+                .collect(),
+            repos: apps
+                .iter()
+                .map(|a| {
+                    let (org, name) = a.split_once('/').unwrap();
+                    Repo {
+                        org: org.to_string(),
+                        name: name.to_string(),
+                        cloned: true,
+                        ..Default::default()
+                    }
+                })
+                .collect(),
+            report: Report {
+                created: Utc::now(),
+                analyzers: Vec::new(),
+                apps: apps.iter().map(|a| a.to_string()).collect(),
+                findings: findings
+                    .iter()
+                    .map(|(app, analyzer, kind, name, value)| {
+                        let (org, app) = app.split_once('/').unwrap();
+                        Finding {
+                            org: org.to_string(),
+                            app: app.to_string(),
+                            analyzer: analyzer.to_string(),
+                            severity: Severity::Info,
+                            kind: kind.to_string(),
+                            name: name.to_string(),
+                            value: value.map(|v| v.to_string()),
+                        }
+                    })
+                    .collect(),
+            },
+        }
+    }
+
+    fn app_lib(app: &'static str, version: &'static str) -> Fixture {
+        (app, "packages", "package", "Altinn.App.Api", Some(version))
+    }
+
+    fn frontend(app: &'static str, version: &'static str) -> Fixture {
+        (app, "frontend", "version", version, None)
+    }
+
+    fn component(app: &'static str, name: &'static str) -> Fixture {
+        (app, "layouts", "component", name, None)
+    }
+
+    #[test]
+    fn lists_added_and_removed_orgs_and_apps() {
+        let from = snapshot(&["digdir", "ttd"], &["ttd/app1", "ttd/app2"], &[]);
+        let to = snapshot(&["skd", "ttd"], &["skd/app1", "ttd/app2", "ttd/app3"], &[]);
+
+        let diff = diff(&from, &to);
+
+        assert_eq!(diff.orgs_added, ["skd"]);
+        assert_eq!(diff.orgs_removed, ["digdir"]);
+        assert_eq!(diff.apps_added, ["skd/app1", "ttd/app3"]);
+        assert_eq!(diff.apps_removed, ["ttd/app1"]);
+    }
+
+    #[test]
+    fn lists_version_changes_of_apps_analyzed_in_both() {
+        let from = snapshot(
+            &["ttd"],
+            &["ttd/app1", "ttd/app2", "ttd/app3"],
+            &[
+                app_lib("ttd/app1", "7.15.0"),
+                frontend("ttd/app1", "3"),
+                app_lib("ttd/app2", "8.0.0"),
+                frontend("ttd/app2", "4"),
+                app_lib("ttd/app3", "7.0.0"),
+            ],
+        );
+        let to = snapshot(
+            &["ttd"],
+            &["ttd/app1", "ttd/app2", "ttd/app4"],
+            &[
+                app_lib("ttd/app1", "8.1.0"),
+                frontend("ttd/app1", "4"),
+                app_lib("ttd/app2", "8.0.0"),
+                app_lib("ttd/app4", "8.1.0"),
+            ],
+        );
+
+        let diff = diff(&from, &to);
+
+        assert_eq!(
+            diff.app_lib_changes,
+            [(
+                "ttd/app1".to_string(),
+                Some("7.15.0".to_string()),
+                Some("8.1.0".to_string())
+            )]
+        );
+        assert_eq!(
+            diff.frontend_changes,
+            [
+                ("ttd/app1".to_string(), Some("3".to_string()), Some("4".to_string())),
+                ("ttd/app2".to_string(), Some("4".to_string()), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn counts_the_analyzed_apps_per_major_version() {
+        let from = snapshot(
+            &["ttd"],
+            &["ttd/app1", "ttd/app2", "ttd/app3"],
+            &[
+                app_lib("ttd/app1", "7.15.0"),
+                app_lib("ttd/app2", "7.0.0"),
+                frontend("ttd/app1", "3"),
+            ],
+        );
+        let to = snapshot(
+            &["ttd"],
+            &["ttd/app1", "ttd/app2", "ttd/app3"],
+            &[
+                app_lib("ttd/app1", "8.1.0"),
+                app_lib("ttd/app2", "7.0.0"),
+                app_lib("ttd/app3", "$(AppLibVersion)"),
+                frontend("ttd/app1", "4.5.2"),
+                frontend("ttd/app2", "4"),
+            ],
+        );
+
+        let diff = diff(&from, &to);
+
+        assert_eq!(
+            diff.app_lib_majors,
+            BTreeMap::from([
+                ("-".to_string(), (1, 0)),
+                ("7".to_string(), (2, 1)),
+                ("8".to_string(), (0, 1)),
+                ("other".to_string(), (0, 1)),
+            ])
+        );
+        assert_eq!(
+            diff.frontend_majors,
+            BTreeMap::from([
+                ("-".to_string(), (2, 1)),
+                ("3".to_string(), (1, 0)),
+                ("4".to_string(), (0, 2)),
+            ])
+        );
+    }
+
+    #[test]
+    fn lists_the_apps_that_started_using_a_component_type() {
+        let from = snapshot(
+            &["ttd"],
+            &["ttd/app1", "ttd/app2"],
+            &[component("ttd/app1", "Input"), component("ttd/app2", "Input")],
+        );
+        let to = snapshot(
+            &["ttd"],
+            &["ttd/app1", "ttd/app2", "ttd/app3"],
+            &[
+                component("ttd/app1", "Input"),
+                component("ttd/app1", "Map"),
+                component("ttd/app2", "Map"),
+                component("ttd/app2", "Likert"),
+                component("ttd/app3", "Grid"),
+            ],
+        );
+
+        let diff = diff(&from, &to);
+
+        // Apps that weren't analyzed before aren't counted as starting to use their components
+        assert_eq!(
+            diff.new_components,
+            BTreeMap::from([
+                ("Likert".to_string(), vec!["ttd/app2".to_string()]),
+                ("Map".to_string(), vec!["ttd/app1".to_string(), "ttd/app2".to_string()]),
+            ])
+        );
+    }
+}
@@ -0,0 +1,63 @@
+use anyhow::anyhow;
+use anyhow::Result;
+use anyhow::{bail, Context};
+use reqwest::{header, Client, ClientBuilder, Url};
+use serde::{Deserialize, Serialize};
+
+/// Client for Altinn Storage, where the metadata of an app is registered when it's deployed to an environment
+pub struct StorageClient {
+    client: Client,
+}
+
+impl StorageClient {
+    pub fn new() -> Self {
+        let mut headers = header::HeaderMap::new();
+        headers.insert("Accept", "application/json".parse().unwrap());
+
+        let client = ClientBuilder::new()
+            .user_agent("altinn-all-apps-rs")
+            .default_headers(headers)
+            .build()
+            .unwrap();
+        StorageClient { client }
+    }
+
+    /// All apps registered in the Storage of the environment at the given platform url
+    pub async fn get_applications(&self, platform_url: &Url) -> Result<Vec<StorageApplication>> {
+        let url = platform_url
+            .join("/storage/api/v1/applications")
+            .context("Failed to build URL")?;
+
+        let response = self.client.get(url).send().await;
+
+        let response = response.context("Failed to fetch applications - send request")?;
+
+        if !response.status().is_success() {
+            bail!("Failed to fetch applications - invalid status={}", response.status());
+        }
+
+        let body = response
+            .text()
+            .await
+            .context("Failed to fetch applications - reading body of request")?;
+
+        let response = serde_json::from_str::<StorageApplications>(&body)
+            .map_err(|err| anyhow!("Failed to parse applications: {:?}\nBody={}", err, body))?;
+
+        Ok(response.applications)
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageApplications {
+    pub applications: Vec<StorageApplication>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageApplication {
+    /// `org/app`, the same as the repo in Gitea
+    pub id: String,
+    pub org: String,
+}